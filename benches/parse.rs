@@ -0,0 +1,87 @@
+//! Times parsing documents of increasing size to show that it grows
+//! linearly. Run it with `cargo bench`.
+//!
+//! If parsing is linear the time per line should stay about the same as the
+//! documents get bigger.
+
+use std::{
+	hint::black_box,
+	time::{Duration, Instant},
+};
+
+use confindent::{Confindent, ConfindentRef, Parser};
+
+const SIZES: [usize; 4] = [10_000, 20_000, 40_000, 80_000];
+const RUNS: u32 = 5;
+
+/// Makes a document with about the given number of lines.
+type Generator = fn(usize) -> String;
+
+/// Lots of keys with no indentation.
+fn wide(lines: usize) -> String {
+	(0..lines).map(|i| format!("Key{i} value {i}\n")).collect()
+}
+
+/// One root with lots of children, each separated by a blank line.
+fn wide_nested(lines: usize) -> String {
+	let mut doc = String::from("Root\n");
+	for i in 0..lines / 2 {
+		doc.push_str(&format!("\tChild{i} value {i}\n\n"));
+	}
+	doc
+}
+
+/// Sections ten levels deep, one after another.
+fn deep(lines: usize) -> String {
+	let mut doc = String::new();
+	for i in 0..lines {
+		let depth = i % 10;
+		doc.push_str(&"\t".repeat(depth));
+		doc.push_str(&format!("Key{i} value\n"));
+	}
+	doc
+}
+
+fn time<F: FnMut()>(mut f: F) -> Duration {
+	let mut best = Duration::MAX;
+	for _ in 0..RUNS {
+		let start = Instant::now();
+		f();
+		best = best.min(start.elapsed());
+	}
+	best
+}
+
+fn report(name: &str, lines: usize, elapsed: Duration) {
+	let per_line = elapsed.as_nanos() as f64 / lines as f64;
+	println!("{name:>27} {lines:>8} lines {elapsed:>12.2?} {per_line:>8.1} ns/line");
+}
+
+fn main() {
+	let shapes: [(&str, Generator); 3] =
+		[("wide", wide), ("wide nested", wide_nested), ("deep", deep)];
+
+	for (shape, generate) in shapes {
+		for lines in SIZES {
+			let doc = generate(lines);
+
+			let owned = time(|| {
+				black_box(doc.parse::<Confindent>().unwrap());
+			});
+			report(&format!("{shape} (Confindent)"), lines, owned);
+
+			let borrowed = time(|| {
+				black_box(ConfindentRef::parse(&doc).unwrap());
+			});
+			report(&format!("{shape} (ConfindentRef)"), lines, borrowed);
+
+			let events = time(|| {
+				for event in Parser::new(doc.as_bytes()) {
+					black_box(event.unwrap());
+				}
+			});
+			report(&format!("{shape} (Parser)"), lines, events);
+		}
+		println!();
+	}
+}
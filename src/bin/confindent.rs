@@ -0,0 +1,359 @@
+//! Inspect and edit confindent files from the command line.
+
+use std::{
+	env, fs,
+	io::{self, Read},
+	process::ExitCode,
+};
+
+use confindent::{
+	schema::{Schema, SchemaError},
+	Confindent, ParseError, ParseErrorKind, ParseOptions, Value, ValueErrorKind,
+};
+
+const USAGE: &str = "\
+Usage: confindent <command> [file]
+
+Reads from stdin if there's no file, or if it's -. Commands that change the
+document write it back to the file, or to stdout if it came from stdin.
+
+Commands:
+	get <path>          Print the value at a / separated path of keys
+	set <path> <value>  Set the value at the path, adding keys if needed
+	rm <path>           Remove the first value at the path, and its children
+	fmt                 Indent with tabs, one per level
+	    --tab-width <n>   mixing tabs and spaces is fine, with tabs n wide
+	check               Report any errors in the document
+	    --schema <file>   and check it against a schema
+	tree                Show the structure of the document
+	convert --to json   Print the document as JSON
+	convert --from <f>  Print a json, ini, or toml file as a document";
+
+enum Failure {
+	/// The arguments were wrong, so show how to use us
+	Usage(String),
+	/// Something went wrong doing what was asked
+	Error(String),
+}
+
+fn main() -> ExitCode {
+	let args: Vec<String> = env::args().skip(1).collect();
+
+	match run(args) {
+		Ok(()) => ExitCode::SUCCESS,
+		Err(Failure::Usage(msg)) => {
+			eprintln!("confindent: {msg}\n\n{USAGE}");
+			ExitCode::from(2)
+		}
+		Err(Failure::Error(msg)) => {
+			eprintln!("confindent: {msg}");
+			ExitCode::FAILURE
+		}
+	}
+}
+
+fn run(mut args: Vec<String>) -> Result<(), Failure> {
+	if args.is_empty() {
+		return Err(Failure::Usage("missing a command".into()));
+	}
+	let command = args.remove(0);
+
+	match command.as_str() {
+		"help" | "-h" | "--help" => {
+			println!("{USAGE}");
+			Ok(())
+		}
+		"get" => {
+			let ([path], file) = positional(args, ["path"])?;
+			let (_, conf) = read(file)?;
+
+			match conf.get_parse::<_, String>(&path) {
+				Ok(value) => println!("{value}"),
+				Err(e) if e.kind == ValueErrorKind::MissingValue => println!(),
+				Err(_) => return Err(Failure::Error(format!("{path}: not found"))),
+			}
+			Ok(())
+		}
+		"set" => {
+			let ([path, value], file) = positional(args, ["path", "value"])?;
+			let (input, mut conf) = read(file)?;
+
+			conf.set(&path, Some(value));
+			write(&input, &conf)
+		}
+		"rm" => {
+			let ([path], file) = positional(args, ["path"])?;
+			let (input, mut conf) = read(file)?;
+
+			if conf.remove(&path).is_none() {
+				return Err(Failure::Error(format!("{path}: not found")));
+			}
+			write(&input, &conf)
+		}
+		"fmt" => {
+			let tab_width = option(&mut args, "--tab-width")?;
+			let ([], file) = positional(args, [])?;
+
+			let options = match tab_width {
+				None => ParseOptions::new(),
+				Some(width) => match width.parse() {
+					Ok(width) => ParseOptions::new().mixed_indent(width),
+					Err(_) => {
+						return Err(Failure::Usage(format!(
+							"--tab-width needs a number, not '{width}'"
+						)))
+					}
+				},
+			};
+			let (input, mut conf) = read_with(file, &options)?;
+			for warning in conf.warnings() {
+				eprintln!("{}", parse_warning(&input.name, warning));
+			}
+
+			conf.normalize();
+			write(&input, &conf)
+		}
+		"check" => {
+			let schema = option(&mut args, "--schema")?;
+			let ([], file) = positional(args, [])?;
+			let (input, conf) = read(file)?;
+
+			let schema = match schema {
+				None => return Ok(()),
+				Some(path) => load_schema(&path)?,
+			};
+
+			let violations = schema.validate(&conf);
+			for violation in &violations {
+				match violation.line {
+					None => eprintln!("{}: {}: {}", input.name, violation.path, violation.kind),
+					Some(line) => eprintln!(
+						"{}:{}: {}: {}",
+						input.name,
+						line + 1,
+						violation.path,
+						violation.kind
+					),
+				}
+			}
+
+			match violations.len() {
+				0 => Ok(()),
+				1 => Err(Failure::Error("1 problem found".into())),
+				count => Err(Failure::Error(format!("{count} problems found"))),
+			}
+		}
+		"tree" => {
+			let ([], file) = positional(args, [])?;
+			let (_, conf) = read(file)?;
+
+			let mut tree = String::new();
+			for value in conf.values() {
+				tree_line(&mut tree, value, "", None);
+			}
+			print!("{tree}");
+			Ok(())
+		}
+		"convert" => {
+			let to = option(&mut args, "--to")?;
+			let from = option(&mut args, "--from")?;
+			let ([], file) = positional(args, [])?;
+
+			match (to.as_deref(), from.as_deref()) {
+				(Some("json"), None) => {
+					let (_, conf) = read(file)?;
+					println!("{}", conf.to_json());
+					Ok(())
+				}
+				(None, Some("json")) => {
+					let (input, text) = read_text(file)?;
+					let conf = Confindent::from_json(&text).map_err(|e| {
+						Failure::Error(format!(
+							"{}:{}:{}: {}",
+							input.name,
+							e.line + 1,
+							e.column + 1,
+							e.kind
+						))
+					})?;
+					print!("{conf}");
+					Ok(())
+				}
+				(None, Some(format @ ("ini" | "toml"))) => {
+					let (input, text) = read_text(file)?;
+					let conf = match format {
+						"ini" => Confindent::from_ini(&text),
+						_ => Confindent::from_toml(&text),
+					};
+					let conf = conf.map_err(|e| {
+						Failure::Error(format!("{}:{}: {}", input.name, e.line + 1, e.kind))
+					})?;
+					print!("{conf}");
+					Ok(())
+				}
+				(Some(format), None) => Err(Failure::Usage(format!("can't convert to '{format}'"))),
+				(None, Some(format)) => {
+					Err(Failure::Usage(format!("can't convert from '{format}'")))
+				}
+				_ => Err(Failure::Usage("convert needs one of --to or --from".into())),
+			}
+		}
+		other => Err(Failure::Usage(format!("unknown command '{other}'"))),
+	}
+}
+
+/// Take an option, and its value, out of the arguments.
+fn option(args: &mut Vec<String>, name: &str) -> Result<Option<String>, Failure> {
+	let Some(idx) = args
+		.iter()
+		.position(|arg| arg == name || arg.starts_with(&format!("{name}=")))
+	else {
+		return Ok(None);
+	};
+
+	let arg = args.remove(idx);
+	match arg.split_once('=') {
+		Some((_, value)) => Ok(Some(value.to_owned())),
+		None if idx < args.len() => Ok(Some(args.remove(idx))),
+		None => Err(Failure::Usage(format!("{name} needs a value"))),
+	}
+}
+
+/// The named arguments the command needs, followed by the optional file.
+fn positional<const N: usize>(
+	args: Vec<String>,
+	names: [&str; N],
+) -> Result<([String; N], Option<String>), Failure> {
+	if let Some(flag) = args.iter().find(|arg| arg.starts_with("--")) {
+		return Err(Failure::Usage(format!("unknown option '{flag}'")));
+	}
+	if args.len() < N {
+		return Err(Failure::Usage(format!("missing the {}", names[args.len()])));
+	}
+	if args.len() > N + 1 {
+		return Err(Failure::Usage(format!(
+			"unexpected argument '{}'",
+			args[N + 1]
+		)));
+	}
+
+	let mut args = args.into_iter();
+	let named = std::array::from_fn(|_| args.next().unwrap_or_default());
+	Ok((named, args.next()))
+}
+
+struct Input {
+	/// What to call the input in messages
+	name: String,
+	/// The file it came from, or None for stdin
+	path: Option<String>,
+}
+
+fn read(file: Option<String>) -> Result<(Input, Confindent), Failure> {
+	read_with(file, &ParseOptions::default())
+}
+
+fn read_with(file: Option<String>, options: &ParseOptions) -> Result<(Input, Confindent), Failure> {
+	let (input, text) = read_text(file)?;
+	let conf = Confindent::from_str_with(&text, options)
+		.map_err(|e| Failure::Error(parse_error(&input.name, e)))?;
+
+	Ok((input, conf))
+}
+
+fn read_text(file: Option<String>) -> Result<(Input, String), Failure> {
+	let path = file.filter(|file| file != "-");
+	let text = match &path {
+		None => {
+			let mut text = String::new();
+			io::stdin()
+				.read_to_string(&mut text)
+				.map_err(|e| Failure::Error(format!("failed to read stdin: {e}")))?;
+			text
+		}
+		Some(path) => fs::read_to_string(path)
+			.map_err(|e| Failure::Error(format!("failed to read {path}: {e}")))?,
+	};
+
+	let input = Input {
+		name: path.clone().unwrap_or_else(|| "<stdin>".into()),
+		path,
+	};
+
+	Ok((input, text))
+}
+
+fn write(input: &Input, conf: &Confindent) -> Result<(), Failure> {
+	match &input.path {
+		None => {
+			print!("{conf}");
+			Ok(())
+		}
+		Some(path) => conf
+			.save(path)
+			.map_err(|e| Failure::Error(format!("failed to write {path}: {e}"))),
+	}
+}
+
+fn load_schema(path: &str) -> Result<Schema, Failure> {
+	let text = fs::read_to_string(path)
+		.map_err(|e| Failure::Error(format!("failed to read {path}: {e}")))?;
+
+	text.parse()
+		.map_err(|e: SchemaError| Failure::Error(format!("{path}: {e}")))
+}
+
+/// A parse error the way compilers write them, with lines starting from one.
+fn parse_error(name: &str, e: ParseError) -> String {
+	format!("{name}:{}: {}", e.line + 1, problem(&e.kind))
+}
+
+/// A line tolerated by --tab-width, written like a parse error.
+fn parse_warning(name: &str, e: &ParseError) -> String {
+	format!("{name}:{}: warning: {}", e.line + 1, problem(&e.kind))
+}
+
+fn problem(kind: &ParseErrorKind) -> String {
+	let problem = match kind {
+		ParseErrorKind::StartedIndented => "the document can't start indented",
+		ParseErrorKind::MixedIndent => "indent mixes tabs and spaces",
+		ParseErrorKind::TabsWithSpaces => "indented with tabs in a block indented with spaces",
+		ParseErrorKind::SpacesWithTabs => "indented with spaces in a block indented with tabs",
+		ParseErrorKind::TabsNotAllowed => "indented with tabs where only spaces are allowed",
+		ParseErrorKind::SpacesNotAllowed => "indented with spaces where only tabs are allowed",
+		ParseErrorKind::InconsistentDedent { block } => {
+			return format!(
+				"doesn't line up with the block starting on line {}",
+				block + 1
+			);
+		}
+		ParseErrorKind::FileReadError => "failed to read",
+	};
+
+	problem.to_owned()
+}
+
+/// Add a value, and everything under it, to the tree. `last` is whether
+/// this is the last of its siblings, or None at the top.
+fn tree_line(tree: &mut String, value: &Value, prefix: &str, last: Option<bool>) {
+	let (branch, extend) = match last {
+		None => ("", ""),
+		Some(false) => ("├── ", "│   "),
+		Some(true) => ("└── ", "    "),
+	};
+
+	tree.push_str(prefix);
+	tree.push_str(branch);
+	tree.push_str(value.key());
+	if let Some(value) = value.value() {
+		tree.push(' ');
+		tree.push_str(value);
+	}
+	tree.push('\n');
+
+	let prefix = format!("{prefix}{extend}");
+	let mut children = value.values().peekable();
+	while let Some(child) = children.next() {
+		tree_line(tree, child, &prefix, Some(children.peek().is_none()));
+	}
+}
@@ -0,0 +1,432 @@
+use core::fmt;
+use std::str::FromStr;
+
+use crate::{
+	error::{ParseError, ValueError},
+	indent::Indent,
+	index::KeyIndex,
+	line::{Line, LineEnding},
+	parser::{Nesting, Node, RawLine, TreeBuilder},
+	typed, Confindent, KeyCase, ParseOptions, Value,
+};
+
+/// A parsed configuration file that borrows from the text it came from.
+///
+/// Every key and value is a slice of the original string, so parsing one
+/// of these doesn't allocate a `String` for each of them like [Confindent]
+/// does. It has the same methods for reading, and if you need to change
+/// something you can turn it into a [Confindent] with
+/// [into_owned](ConfindentRef::into_owned).
+///
+/// # Example
+///
+/// ```rust
+/// use confindent::ConfindentRef;
+///
+/// let text = String::from("Host example.com\n\tPort 22");
+/// let conf = ConfindentRef::parse(&text).unwrap();
+///
+/// let host = conf.child("Host").unwrap();
+/// assert_eq!(host.value(), Some("example.com"));
+/// assert_eq!(host.child_parse("Port"), Ok(22));
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+pub struct ConfindentRef<'a> {
+	children: Vec<LineRef<'a>>,
+	case: KeyCase,
+	bom: bool,
+	line_ending: LineEnding,
+	final_newline: bool,
+	warnings: Vec<ParseError>,
+}
+
+/// A line of a [ConfindentRef]. Comments and blank lines don't need
+/// anything more than what the parser gave us.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) enum LineRef<'a> {
+	Value(ValueRef<'a>),
+	Other(RawLine<'a>),
+}
+
+/// A value borrowed from the text of a [ConfindentRef]. See [Value].
+#[derive(Clone, Debug, PartialEq)]
+pub struct ValueRef<'a> {
+	indent: Indent,
+	written_indent: Option<&'a str>,
+	key: &'a str,
+	value: Option<&'a str>,
+	separator: Option<&'a str>,
+	ending: Option<LineEnding>,
+	line: usize,
+	children: Vec<LineRef<'a>>,
+	case: KeyCase,
+}
+
+impl<'a> ConfindentRef<'a> {
+	/// Parse a document, borrowing keys and values from `text`.
+	pub fn parse(text: &'a str) -> Result<Self, ParseError> {
+		Self::parse_with(text, &ParseOptions::default())
+	}
+
+	/// Parse a document the way the options say to. See [ParseOptions].
+	pub fn parse_with(text: &'a str, options: &ParseOptions) -> Result<Self, ParseError> {
+		let mut nesting = Nesting::new(options.clone());
+		let mut tree = TreeBuilder::<ValueRef>::new();
+
+		for raw in text.split_inclusive('\n') {
+			let placed = nesting.line(raw)?.map(|line| match line {
+				RawLine::Value {
+					indent,
+					written_indent,
+					key,
+					value,
+					separator,
+					ending,
+					number,
+				} => LineRef::Value(ValueRef {
+					indent,
+					written_indent,
+					key,
+					value,
+					separator,
+					ending,
+					line: number,
+					children: vec![],
+					case: KeyCase::Sensitive,
+				}),
+				other => LineRef::Other(other),
+			});
+
+			tree.push(placed);
+		}
+
+		Ok(Self {
+			children: tree.finish(),
+			case: KeyCase::Sensitive,
+			bom: nesting.bom,
+			line_ending: nesting.line_ending.unwrap_or_default(),
+			final_newline: nesting.final_newline,
+			warnings: nesting.warnings,
+		})
+	}
+
+	/// Lines tolerated by [mixed_indent](ParseOptions::mixed_indent). See
+	/// [Confindent::warnings].
+	pub fn warnings(&self) -> &[ParseError] {
+		&self.warnings
+	}
+
+	/// Copy everything into a [Confindent] you can change.
+	pub fn into_owned(self) -> Confindent {
+		let mut conf = Confindent {
+			children: self.children.into_iter().map(LineRef::into_owned).collect(),
+			index: KeyIndex::default(),
+			bom: self.bom,
+			line_ending: self.line_ending,
+			final_newline: self.final_newline,
+			warnings: self.warnings,
+		};
+		conf.set_key_case(self.case);
+		conf
+	}
+
+	/// How keys are compared when looking them up.
+	pub fn key_case(&self) -> KeyCase {
+		self.case
+	}
+
+	/// Compare keys the provided way when looking anything up.
+	///
+	/// See [Confindent::set_key_case] for more.
+	pub fn set_key_case(&mut self, case: KeyCase) {
+		self.case = case;
+		set_case(&mut self.children, case);
+	}
+
+	/// Get a value from a `/` separated path of keys.
+	///
+	/// See [Confindent::get] for more.
+	pub fn get<S: AsRef<str>>(&self, path: S) -> Option<&'a str> {
+		self.walk(path.as_ref())?.value()
+	}
+
+	/// Get and parse a value from a `/` separated path of keys.
+	pub fn get_parse<S: AsRef<str>, T>(&self, path: S) -> Result<T, ValueError>
+	where
+		T: FromStr,
+		T::Err: fmt::Display,
+	{
+		let path = path.as_ref();
+		typed::typed(self.walk(path), path, typed::from_str)
+	}
+
+	fn walk(&self, path: &str) -> Option<&ValueRef<'a>> {
+		let (first, rest) = path.split_once('/').unwrap_or((path, ""));
+		let current = self.child(first)?;
+
+		match rest {
+			"" => Some(current),
+			rest => current.walk(rest),
+		}
+	}
+
+	/// Get a child with the provided key.
+	///
+	/// See [Value::child] for more.
+	pub fn child<S: AsRef<str>>(&self, key: S) -> Option<&ValueRef<'a>> {
+		values(&self.children).find(|value| self.case.matches(value.key, key.as_ref()))
+	}
+
+	/// Get all of the direct children with the provided key.
+	///
+	/// See [Value::children] for more.
+	pub fn children<S: AsRef<str>>(&self, key: S) -> Vec<&ValueRef<'a>> {
+		values(&self.children)
+			.filter(|value| self.case.matches(value.key, key.as_ref()))
+			.collect()
+	}
+
+	/// Check if there are any direct children with the provided key.
+	///
+	/// See [Value::has_child] for more.
+	pub fn has_child<S: AsRef<str>>(&self, key: S) -> bool {
+		values(&self.children).any(|value| self.case.matches(value.key, key.as_ref()))
+	}
+
+	/// Get the value of a child with the provided key.
+	///
+	/// See [Value::child_value] for more.
+	pub fn child_value<S: AsRef<str>>(&self, key: S) -> Option<&'a str> {
+		self.child(key).and_then(|child| child.value())
+	}
+
+	/// Parse the value of a child into your desired type.
+	///
+	/// See [Value::child_parse] for more.
+	pub fn child_parse<S: AsRef<str>, T>(&self, key: S) -> Result<T, ValueError>
+	where
+		T: FromStr,
+		T::Err: fmt::Display,
+	{
+		let key = key.as_ref();
+		typed::typed(self.child(key), key, typed::from_str)
+	}
+
+	/// Every value with no indentation, in order.
+	pub fn values(&self) -> impl Iterator<Item = &ValueRef<'a>> {
+		values(&self.children)
+	}
+}
+
+impl<'a> ValueRef<'a> {
+	/// The key of this value.
+	pub fn key(&self) -> &'a str {
+		self.key
+	}
+
+	/// The line this value was on, starting from zero.
+	pub fn line(&self) -> usize {
+		self.line
+	}
+
+	/// Gets the contained value.
+	///
+	/// See [Value::value] for more.
+	pub fn value(&self) -> Option<&'a str> {
+		self.value
+	}
+
+	/// Parse the contained value into your type.
+	///
+	/// See [Value::parse] for more.
+	pub fn parse<T>(&self) -> Result<T, ValueError>
+	where
+		T: FromStr,
+		T::Err: fmt::Display,
+	{
+		typed::typed(Some(self), self.key, typed::from_str)
+	}
+
+	/// Get a value from a `/` separated path of keys.
+	///
+	/// See [Value::get] for more.
+	pub fn get<S: AsRef<str>>(&self, path: S) -> Option<&'a str> {
+		self.walk(path.as_ref())?.value()
+	}
+
+	/// Get and parse a value from a `/` separated path of keys.
+	pub fn get_parse<S: AsRef<str>, T>(&self, path: S) -> Result<T, ValueError>
+	where
+		T: FromStr,
+		T::Err: fmt::Display,
+	{
+		let path = path.as_ref();
+		typed::typed(self.walk(path), path, typed::from_str)
+	}
+
+	fn walk(&self, path: &str) -> Option<&ValueRef<'a>> {
+		let mut current = self;
+		for key in path.split('/') {
+			current = current.child(key)?;
+		}
+
+		Some(current)
+	}
+
+	/// Get the first child with the provided key.
+	///
+	/// See [Value::child] for more.
+	pub fn child<S: AsRef<str>>(&self, key: S) -> Option<&ValueRef<'a>> {
+		values(&self.children).find(|value| self.case.matches(value.key, key.as_ref()))
+	}
+
+	/// Get every direct child with the provided key.
+	///
+	/// See [Value::children] for more.
+	pub fn children<S: AsRef<str>>(&self, key: S) -> Vec<&ValueRef<'a>> {
+		values(&self.children)
+			.filter(|value| self.case.matches(value.key, key.as_ref()))
+			.collect()
+	}
+
+	/// Check if there are any direct children with the provided key.
+	///
+	/// See [Value::has_child] for more.
+	pub fn has_child<S: AsRef<str>>(&self, key: S) -> bool {
+		values(&self.children).any(|value| self.case.matches(value.key, key.as_ref()))
+	}
+
+	/// Get the value of the first child with the provided key.
+	///
+	/// See [Value::child_value] for more.
+	pub fn child_value<S: AsRef<str>>(&self, key: S) -> Option<&'a str> {
+		self.child(key).and_then(|child| child.value())
+	}
+
+	/// Parse the value of the first child with the provided key.
+	///
+	/// See [Value::child_parse] for more.
+	pub fn child_parse<S: AsRef<str>, T>(&self, key: S) -> Result<T, ValueError>
+	where
+		T: FromStr,
+		T::Err: fmt::Display,
+	{
+		let key = key.as_ref();
+		typed::typed(self.child(key), key, typed::from_str)
+	}
+
+	/// Every direct child, in order.
+	pub fn values(&self) -> impl Iterator<Item = &ValueRef<'a>> {
+		values(&self.children)
+	}
+
+	/// Copy this value, and its children, into a [Value].
+	pub fn into_owned(self) -> Value {
+		let mut value = Value {
+			indent: self.indent,
+			key: self.key.to_owned(),
+			value: self.value.map(<_>::to_owned),
+			separator: self.separator.map(<_>::to_owned),
+			ending: self.ending,
+			line: Some(self.line),
+			children: self.children.into_iter().map(LineRef::into_owned).collect(),
+			index: KeyIndex::default(),
+			secret: false,
+			inherited: false,
+			written_indent: self.written_indent.map(<_>::to_owned),
+		};
+		value.set_key_case(self.case);
+		value
+	}
+}
+
+impl LineRef<'_> {
+	fn into_owned(self) -> Line {
+		match self {
+			LineRef::Value(value) => Line::Value(value.into_owned()),
+			LineRef::Other(raw) => raw.into(),
+		}
+	}
+}
+
+impl<'a> Node for ValueRef<'a> {
+	type Line = LineRef<'a>;
+
+	fn from_line(line: LineRef<'a>) -> Result<Self, LineRef<'a>> {
+		match line {
+			LineRef::Value(value) => Ok(value),
+			other => Err(other),
+		}
+	}
+
+	fn children_mut(&mut self) -> &mut Vec<LineRef<'a>> {
+		&mut self.children
+	}
+}
+
+impl<'a> From<ValueRef<'a>> for LineRef<'a> {
+	fn from(value: ValueRef<'a>) -> Self {
+		LineRef::Value(value)
+	}
+}
+
+impl<'a> From<ConfindentRef<'a>> for Confindent {
+	fn from(conf: ConfindentRef<'a>) -> Self {
+		conf.into_owned()
+	}
+}
+
+fn values<'r, 'a>(lines: &'r [LineRef<'a>]) -> impl Iterator<Item = &'r ValueRef<'a>> {
+	lines.iter().filter_map(|line| match line {
+		LineRef::Value(value) => Some(value),
+		LineRef::Other(_) => None,
+	})
+}
+
+fn set_case(lines: &mut [LineRef], case: KeyCase) {
+	for line in lines {
+		if let LineRef::Value(value) = line {
+			value.case = case;
+			set_case(&mut value.children, case);
+		}
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	#[test]
+	fn borrows_from_text() {
+		let text = "Root value\n\tKey v\n\t\tDeep down\nOther";
+		let conf = ConfindentRef::parse(text).unwrap();
+
+		let key = conf.child("Root").unwrap().child("Key").unwrap();
+		let range = text.as_bytes().as_ptr_range();
+		assert!(range.contains(&key.key().as_ptr()));
+		assert!(range.contains(&key.value().unwrap().as_ptr()));
+
+		assert_eq!(conf.get("Root/Key/Deep"), Some("down"));
+		assert!(conf.has_child("Other"));
+	}
+
+	#[test]
+	fn into_owned_matches_owned_parse() {
+		let text = "\u{feff}# Comment\r\nRoot value\r\n\tKey v\r\n\r\n\t# Comment\r\n\tKey w\nEnd";
+
+		let borrowed = ConfindentRef::parse(text).unwrap();
+		let owned: Confindent = text.parse().unwrap();
+
+		assert_eq!(borrowed.into_owned(), owned);
+	}
+
+	#[test]
+	fn same_errors() {
+		let text = "Root\n\tKey\n  Spaced";
+
+		assert_eq!(
+			ConfindentRef::parse(text).unwrap_err(),
+			text.parse::<Confindent>().unwrap_err()
+		);
+	}
+}
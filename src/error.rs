@@ -1,19 +1,32 @@
 use std::error::Error as StdError;
-use std::fmt::{self, Debug};
-use std::str::FromStr;
+use std::fmt;
 
 /// What kind of error happened? Oh, ParseErrorKind of error.
-#[derive(Debug, PartialEq)]
+#[derive(Clone, Debug, PartialEq)]
 pub enum ParseErrorKind {
 	StartedIndented,
 	MixedIndent,
 	TabsWithSpaces,
 	SpacesWithTabs,
+	/// Indented with tabs when [ParseOptions](crate::ParseOptions) only
+	/// allow spaces
+	TabsNotAllowed,
+	/// Indented with spaces when [ParseOptions](crate::ParseOptions) only
+	/// allow tabs
+	SpacesNotAllowed,
+	/// A value is less indented than the one before it, but doesn't line up
+	/// with any of the values it's in. This is only an error with
+	/// [strict_dedent](crate::ParseOptions::strict_dedent).
+	InconsistentDedent {
+		/// The line of the first value that's more indented than this one,
+		/// which is the block it should have lined up with
+		block: usize,
+	},
 	FileReadError,
 }
 
 /// Our main error type.
-#[derive(Debug, PartialEq)]
+#[derive(Clone, Debug, PartialEq)]
 pub struct ParseError {
 	pub line: usize,
 	pub kind: ParseErrorKind,
@@ -43,6 +56,27 @@ impl fmt::Display for ParseError {
 			ParseErrorKind::SpacesWithTabs => {
 				write!(f, "Space indent in tab block. Line {}", self.line)
 			}
+			ParseErrorKind::TabsNotAllowed => {
+				write!(
+					f,
+					"Tabular indent where only spaces are allowed. Line {}",
+					self.line
+				)
+			}
+			ParseErrorKind::SpacesNotAllowed => {
+				write!(
+					f,
+					"Space indent where only tabs are allowed. Line {}",
+					self.line
+				)
+			}
+			ParseErrorKind::InconsistentDedent { block } => {
+				write!(
+					f,
+					"Dedent doesn't line up with the block starting on line {block}. Line {}",
+					self.line
+				)
+			}
 			ParseErrorKind::FileReadError => {
 				write!(f, "Failed to open file!")
 			}
@@ -50,60 +84,111 @@ impl fmt::Display for ParseError {
 	}
 }
 
-/// Error returned when parsing a value fails
+/// Error returned when getting a value, or parsing it into a type, fails.
+///
+/// It has enough context to tell someone where in their file to look: the
+/// path that was asked for, the line it's on, and the value as it's written.
+///
+/// ```rust
+/// use confindent::{Confindent, ValueErrorKind};
+///
+/// let conf: Confindent = "Host\n\tPort twenty-two".parse().unwrap();
+/// let err = conf.get_parse::<_, u16>("Host/Port").unwrap_err();
 ///
-/// ValueParseError will implement `Display`, `Debug`, `PartialEq`, and `Error`
-/// as long `<T as FromStr>::Err` implements them.
-pub enum ValueParseError<T: FromStr> {
-	/// There was no value present to even try and parse
-	NoValue,
-	/// A value was present but the parse failed. The error is in this enum tuple.
-	ParseError(<T as FromStr>::Err),
+/// assert_eq!(err.line, Some(1));
+/// assert_eq!(
+/// 	err.to_string(),
+/// 	"Host/Port on line 1: expected a u16 but found 'twenty-two' (invalid digit found in string)"
+/// );
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+pub struct ValueError {
+	/// The path that was asked for
+	pub path: String,
+	/// The line the value is on, if it was parsed from a document and found
+	pub line: Option<usize>,
+	/// The value as it's written, if there was one
+	pub raw: Option<String>,
+	pub kind: ValueErrorKind,
 }
 
-impl<T: FromStr> StdError for ValueParseError<T> where <T as FromStr>::Err: StdError {}
-
-impl<T: FromStr> fmt::Display for ValueParseError<T>
-where
-	<T as FromStr>::Err: fmt::Display,
-{
-	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-		match self {
-			ValueParseError::NoValue => write!(f, "There was no value to parse present"),
-			ValueParseError::ParseError(e) => {
-				write!(f, "Failed to parse configuration value: {}", e)
-			}
-		}
-	}
+/// What went wrong getting a typed value.
+#[derive(Clone, Debug, PartialEq)]
+pub enum ValueErrorKind {
+	/// There's no key at the path
+	MissingKey,
+	/// The key is there but doesn't have a value
+	MissingValue,
+	/// The value couldn't be parsed with [FromStr](std::str::FromStr)
+	Invalid {
+		/// The type it was being parsed into, like `u16` or `IpAddr`
+		type_name: String,
+		/// What the type's parser had to say about it
+		message: String,
+	},
+	/// The value isn't something like `yes` or `off`
+	InvalidBool,
+	/// The value isn't something like `30s` or `1h30m`
+	InvalidDuration,
+	/// The value isn't something like `512` or `10MiB`
+	InvalidBytes,
+	/// The value is the right shape but too big to hold
+	TooLarge,
+	/// A list has a quote that doesn't end
+	UnclosedQuote,
+	/// A list ends with a backslash that has nothing to escape
+	TrailingBackslash,
+	/// An element of a list couldn't be parsed
+	InvalidElement {
+		/// Where the element is in the list, starting from zero
+		index: usize,
+		element: String,
+		/// What the type's parser had to say about it
+		message: String,
+	},
 }
 
-impl<T: FromStr> fmt::Debug for ValueParseError<T>
-where
-	<T as FromStr>::Err: fmt::Debug,
-{
+impl StdError for ValueError {}
+impl fmt::Display for ValueError {
 	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-		match self {
-			ValueParseError::NoValue => f.debug_tuple("NoValue").finish(),
-			ValueParseError::ParseError(e) => f.debug_tuple("ParseError").field(e).finish(),
+		match self.line {
+			None => write!(f, "{}: ", self.path)?,
+			Some(line) => write!(f, "{} on line {line}: ", self.path)?,
 		}
-	}
-}
 
-impl<T: FromStr> PartialEq for ValueParseError<T>
-where
-	<T as FromStr>::Err: PartialEq,
-{
-	fn eq(&self, other: &Self) -> bool {
-		if self == &Self::NoValue && other == &Self::NoValue {
-			true
-		} else if let Self::ParseError(e0) = self {
-			if let Self::ParseError(e1) = other {
-				e0 == e1
-			} else {
-				false
+		let raw = self.raw.as_deref().unwrap_or_default();
+		match self.kind {
+			ValueErrorKind::MissingKey => write!(f, "not found"),
+			ValueErrorKind::MissingValue => write!(f, "needs a value"),
+			ValueErrorKind::Invalid {
+				ref type_name,
+				ref message,
+			} => write!(f, "expected a {type_name} but found '{raw}' ({message})"),
+			ValueErrorKind::InvalidBool => {
+				write!(
+					f,
+					"expected yes, no, on, off, true, false, 1, or 0 but found '{raw}'"
+				)
+			}
+			ValueErrorKind::InvalidDuration => {
+				write!(f, "expected a duration like 30s or 1h30m but found '{raw}'")
+			}
+			ValueErrorKind::InvalidBytes => {
+				write!(
+					f,
+					"expected a size like 512, 64KB, or 10MiB but found '{raw}'"
+				)
+			}
+			ValueErrorKind::TooLarge => write!(f, "'{raw}' is too large"),
+			ValueErrorKind::UnclosedQuote => write!(f, "'{raw}' has a quote that isn't closed"),
+			ValueErrorKind::TrailingBackslash => {
+				write!(f, "'{raw}' ends with a backslash that escapes nothing")
 			}
-		} else {
-			false
+			ValueErrorKind::InvalidElement {
+				index,
+				ref element,
+				ref message,
+			} => write!(f, "element {index}, '{element}', is invalid: {message}"),
 		}
 	}
 }
@@ -0,0 +1,185 @@
+//! Converting INI and TOML files to documents.
+//!
+//! Both formats are line based, so they share an error with the line the
+//! problem is on, and a [Builder] that keeps comments where they were.
+
+use core::fmt;
+use std::error::Error as StdError;
+
+use crate::{indent::Indent, line::Line, Confindent, Value};
+
+mod ini;
+mod toml;
+
+/// The error returned when an INI or TOML file can't be turned into a
+/// [Confindent].
+#[derive(Clone, Debug, PartialEq)]
+pub struct ImportError {
+	/// The line the problem is on, starting from zero
+	pub line: usize,
+	pub kind: ImportErrorKind,
+}
+
+/// What was wrong with the file.
+#[derive(Clone, Debug, PartialEq)]
+pub enum ImportErrorKind {
+	/// It isn't valid in its format. The message says what was expected.
+	Syntax(&'static str),
+	/// A key that can't be written, like one that's empty or has whitespace
+	InvalidKey(String),
+	/// A string with a line break in it, because values are one line
+	MultilineValue,
+	/// An array in an array, which would need a key without a name
+	NestedArray,
+}
+
+/// Builds the document a line at a time. Everything goes under a root value
+/// so tables can be found the same way at every depth.
+struct Builder {
+	root: Value,
+	/// Comments and blank lines waiting to find out which value they belong
+	/// under. They go with whatever comes after them.
+	pending: Vec<Line>,
+}
+
+impl Builder {
+	fn new() -> Self {
+		Builder {
+			root: Value::new("", None::<&str>),
+			pending: vec![],
+		}
+	}
+
+	fn comment(&mut self, comment: &str) {
+		self.pending.push(Line::from((Indent::Empty, comment)));
+	}
+
+	fn blank(&mut self) {
+		self.pending.push(Line::Blank {
+			whitespace: String::new(),
+			ending: None,
+		});
+	}
+
+	/// The value at the path, adding any keys that aren't there. Where a key
+	/// is there more than once it's the last of them, like TOML wants for
+	/// arrays of tables.
+	fn table(&mut self, path: &[String]) -> &mut Value {
+		let mut value = &mut self.root;
+		for key in path {
+			value = last_or_add(value, key);
+		}
+		value
+	}
+
+	/// The value at the path, adding any keys that aren't there. Anything
+	/// pending goes before the first key that's added, or at the end of the
+	/// value if they're all there already.
+	fn open(&mut self, path: &[String]) -> &mut Value {
+		let mut pending = Some(std::mem::take(&mut self.pending));
+		let mut value = &mut self.root;
+
+		for key in path {
+			let there = value
+				.children
+				.iter()
+				.any(|line| matches!(line, Line::Value(v) if v.key == *key));
+			if !there {
+				for line in pending.take().into_iter().flatten() {
+					value.push_line(line);
+				}
+			}
+			value = last_or_add(value, key);
+		}
+
+		for line in pending.into_iter().flatten() {
+			value.push_line(line);
+		}
+		value
+	}
+
+	/// Add a value under the one at `path`, after anything pending.
+	fn add(&mut self, path: &[String], value: Value) {
+		self.open(path).add_child(value);
+	}
+
+	/// We're leaving the table at `path` for another one. Anything pending
+	/// before the last blank line stays with it, and the rest goes with
+	/// whatever's next.
+	fn close(&mut self, path: &[String]) {
+		let Some(blank) = self
+			.pending
+			.iter()
+			.rposition(|line| matches!(line, Line::Blank { .. }))
+		else {
+			return;
+		};
+
+		let rest = self.pending.split_off(blank);
+		let before = std::mem::replace(&mut self.pending, rest);
+		let table = self.table(path);
+		for line in before {
+			table.push_line(line);
+		}
+	}
+
+	fn finish(mut self) -> Confindent {
+		let pending = std::mem::take(&mut self.pending);
+		self.root.children.extend(pending);
+
+		let mut conf = Confindent::new();
+		for line in self.root.children {
+			conf.push_line(line);
+		}
+		conf
+	}
+}
+
+fn last_or_add<'a>(value: &'a mut Value, key: &str) -> &'a mut Value {
+	let idx = value
+		.children
+		.iter()
+		.rposition(|line| matches!(line, Line::Value(v) if v.key == key));
+
+	let idx = match idx {
+		Some(idx) => idx,
+		None => {
+			value.add_child(Value::new(key, None::<&str>));
+			value.children.len() - 1
+		}
+	};
+
+	match &mut value.children[idx] {
+		Line::Value(value) => value,
+		_ => unreachable!(),
+	}
+}
+
+/// Keys can't be empty, have whitespace, or look like a comment.
+fn check_key(key: &str, line: usize) -> Result<(), ImportError> {
+	if key.is_empty() || key.starts_with('#') || key.chars().any(|c| c.is_whitespace()) {
+		return Err(ImportError {
+			line,
+			kind: ImportErrorKind::InvalidKey(key.to_owned()),
+		});
+	}
+	Ok(())
+}
+
+impl StdError for ImportError {}
+impl fmt::Display for ImportError {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		write!(f, "line {}: {}", self.line, self.kind)
+	}
+}
+
+impl fmt::Display for ImportErrorKind {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			ImportErrorKind::Syntax(message) => write!(f, "{message}"),
+			ImportErrorKind::InvalidKey(key) => write!(f, "'{key}' can't be a key"),
+			ImportErrorKind::MultilineValue => write!(f, "values can't have line breaks"),
+			ImportErrorKind::NestedArray => write!(f, "arrays can't be in arrays"),
+		}
+	}
+}
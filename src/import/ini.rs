@@ -0,0 +1,135 @@
+use super::{check_key, Builder, ImportError, ImportErrorKind};
+use crate::{Confindent, Value};
+
+impl Confindent {
+	/// Build a document from an INI file.
+	///
+	/// Every section is a value with its keys as children, and keys before
+	/// the first section are at the top. A section name with a space in it,
+	/// like git's `[remote "origin"]`, is split there into a key and a value,
+	/// and quotes around the value are dropped. Keys are separated from
+	/// their values by an `=` or a `:`, whichever is first, and a key by
+	/// itself is a value without one.
+	///
+	/// Comments starting with a `;` or a `#` are kept, as are blank lines.
+	/// Values are kept as they're written, quotes and all, so something
+	/// after a value that looks like a comment is still part of it.
+	///
+	/// Keys with whitespace in them can't be written and are an error, and
+	/// so is a section without its closing `]`.
+	///
+	/// # Example
+	///
+	/// ```rust
+	/// use confindent::Confindent;
+	///
+	/// let ini = "; the main server\n[server]\nhost = example.com\nport: 22\n\n[remote \"origin\"]\nurl = a.git";
+	/// let conf = Confindent::from_ini(ini).unwrap();
+	///
+	/// assert_eq!(
+	/// 	conf.to_string(),
+	/// 	"# the main server\nserver\n\thost example.com\n\tport 22\n\nremote origin\n\turl a.git\n"
+	/// );
+	/// ```
+	pub fn from_ini(ini: &str) -> Result<Self, ImportError> {
+		let mut builder = Builder::new();
+		let mut section = vec![];
+
+		for (idx, line) in ini.lines().enumerate() {
+			let line = line.trim();
+
+			if line.is_empty() {
+				builder.blank();
+			} else if let Some(comment) = line.strip_prefix([';', '#']) {
+				builder.comment(comment);
+			} else if let Some(header) = line.strip_prefix('[') {
+				let (name, rest) = header.split_once(']').ok_or(ImportError {
+					line: idx,
+					kind: ImportErrorKind::Syntax("expected a ] to close the section"),
+				})?;
+
+				let name = name.trim();
+				let (key, value) = match name.split_once(char::is_whitespace) {
+					None => (name, None),
+					Some((key, value)) => {
+						let value = value.trim();
+						let unquoted = value
+							.strip_prefix('"')
+							.and_then(|value| value.strip_suffix('"'));
+						(key, Some(unquoted.unwrap_or(value)))
+					}
+				};
+				check_key(key, idx)?;
+
+				builder.close(&section);
+				builder.add(&[], Value::new(key, value));
+				section = vec![key.to_owned()];
+
+				// A comment after the header goes with the section's keys
+				let rest = rest.trim_start();
+				match rest.strip_prefix([';', '#']) {
+					Some(comment) => builder.comment(comment),
+					None if rest.is_empty() => (),
+					None => {
+						return Err(ImportError {
+							line: idx,
+							kind: ImportErrorKind::Syntax("expected the end of the line"),
+						})
+					}
+				}
+			} else {
+				let (key, value) = match line.find(['=', ':']) {
+					None => (line, None),
+					Some(at) => {
+						let value = line[at + 1..].trim_start();
+						(line[..at].trim_end(), Some(value).filter(|v| !v.is_empty()))
+					}
+				};
+				check_key(key, idx)?;
+
+				builder.add(&section, Value::new(key, value));
+			}
+		}
+
+		Ok(builder.finish())
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	#[test]
+	fn sections_and_comments() {
+		let ini = "top = 1\r\n\r\n# about a\r\n[a] ; first\r\nx=1\r\nflag\r\nempty =\r\n; trailing\r\n\r\n[a]\r\n[b]\r\n  indented = yes ; not a comment\r\n";
+		let conf = Confindent::from_ini(ini).unwrap();
+
+		assert_eq!(
+			conf.to_string(),
+			"top 1\n\n# about a\na\n\t# first\n\tx 1\n\tflag\n\tempty\n\t# trailing\n\na\nb\n\tindented yes ; not a comment\n"
+		);
+		assert_eq!(conf.children("a").len(), 2);
+	}
+
+	#[test]
+	fn unmappable_ini() {
+		let err = |ini| Confindent::from_ini(ini).unwrap_err();
+
+		assert_eq!(
+			err("[a]\nmy key = 1"),
+			ImportError {
+				line: 1,
+				kind: ImportErrorKind::InvalidKey("my key".into())
+			}
+		);
+		assert_eq!(
+			err("[a\nx = 1").kind,
+			ImportErrorKind::Syntax("expected a ] to close the section")
+		);
+		assert_eq!(
+			err("[a] x = 1").kind,
+			ImportErrorKind::Syntax("expected the end of the line")
+		);
+		assert_eq!(err("= 1").kind, ImportErrorKind::InvalidKey("".into()));
+	}
+}
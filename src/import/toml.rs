@@ -0,0 +1,590 @@
+use super::{check_key, last_or_add, Builder, ImportError, ImportErrorKind};
+use crate::{Confindent, Value};
+
+impl Confindent {
+	/// Build a document from a TOML file.
+	///
+	/// Tables are values with their keys as children, nested as deep as
+	/// their names are dotted, and every table in an array of tables is
+	/// another value with the same key. Dotted keys nest the same way, and
+	/// inline tables are values with children too. An array is its key once
+	/// for each element, and an empty one isn't there at all.
+	///
+	/// Strings are unescaped and everything else is kept as it's written,
+	/// except for the underscores between digits. An empty string is the
+	/// same as no value at all.
+	///
+	/// Comments and blank lines between keys are kept. A comment after a
+	/// value on the same line goes on its own line before it, and comments
+	/// inside arrays are dropped.
+	///
+	/// Some TOML can't be a document, like strings with line breaks or
+	/// arrays in arrays, and is an error. See [ImportErrorKind] for all of
+	/// them. The file is only checked as much as it needs to be converted,
+	/// so some invalid TOML, like a table defined twice, gets through.
+	///
+	/// # Example
+	///
+	/// ```rust
+	/// use confindent::Confindent;
+	///
+	/// let toml = "title = \"Example\"\n\n[server.ssh]\nport = 22 # the default\n\n[[user]]\nname = \"gen\"\ngroups = [\"wheel\", \"audio\"]";
+	/// let conf = Confindent::from_toml(toml).unwrap();
+	///
+	/// assert_eq!(
+	/// 	conf.to_string(),
+	/// 	"title Example\n\nserver\n\tssh\n\t\t# the default\n\t\tport 22\n\nuser\n\tname gen\n\tgroups wheel\n\tgroups audio\n"
+	/// );
+	/// ```
+	pub fn from_toml(toml: &str) -> Result<Self, ImportError> {
+		let mut reader = Reader {
+			text: toml,
+			pos: 0,
+			line: 0,
+		};
+		let mut builder = Builder::new();
+		let mut table = vec![];
+
+		loop {
+			reader.spaces();
+			match reader.peek() {
+				None => break,
+				Some('\r' | '\n') => {
+					reader.newline()?;
+					builder.blank();
+				}
+				Some('#') => {
+					let comment = reader.comment();
+					builder.comment(comment);
+					reader.end_of_line()?;
+				}
+				Some('[') => {
+					reader.bump();
+					let array = reader.eat('[');
+					reader.spaces();
+					let path = reader.key()?;
+					reader.expect(']', "expected a ] to close the table")?;
+					if array {
+						reader.expect(']', "expected a ]] to close the array of tables")?;
+					}
+
+					builder.close(&table);
+					match path.split_last() {
+						Some((key, parent)) if array => {
+							builder.add(parent, Value::new(key, None::<&str>))
+						}
+						_ => {
+							builder.open(&path);
+						}
+					}
+					if let Some(comment) = reader.trailing_comment()? {
+						builder.comment(comment);
+					}
+					table = path;
+				}
+				Some(_) => {
+					let mut keys = reader.key()?;
+					reader.expect('=', "expected an = after the key")?;
+					reader.spaces();
+					let item = reader.value()?;
+
+					if let Some(comment) = reader.trailing_comment()? {
+						builder.comment(comment);
+					}
+
+					let key = keys.pop().unwrap_or_default();
+					let path: Vec<String> = table.iter().cloned().chain(keys).collect();
+					for value in item.into_values(&key)? {
+						builder.add(&path, value);
+					}
+				}
+			}
+		}
+
+		Ok(builder.finish())
+	}
+}
+
+/// A TOML value, after it's read and before it's a [Value].
+enum Item {
+	/// Anything that isn't an array or a table, as it'll be written
+	Scalar(Option<String>),
+	/// The elements, and the line each starts on
+	Array(Vec<(usize, Item)>),
+	/// The dotted keys of each member, and its value
+	Table(Vec<(Vec<String>, Item)>),
+}
+
+impl Item {
+	/// The values this item is with `key`. Arrays are one for each element.
+	fn into_values(self, key: &str) -> Result<Vec<Value>, ImportError> {
+		match self {
+			Item::Array(elements) => elements
+				.into_iter()
+				.map(|(line, element)| match element {
+					Item::Array(_) => Err(ImportError {
+						line,
+						kind: ImportErrorKind::NestedArray,
+					}),
+					_ => element.into_value(key),
+				})
+				.collect(),
+			item => Ok(vec![item.into_value(key)?]),
+		}
+	}
+
+	fn into_value(self, key: &str) -> Result<Value, ImportError> {
+		match self {
+			Item::Scalar(scalar) => Ok(Value::new(key, scalar)),
+			Item::Table(members) => {
+				let mut table = Value::new(key, None::<&str>);
+				for (mut keys, item) in members {
+					let key = keys.pop().unwrap_or_default();
+
+					let mut parent = &mut table;
+					for key in &keys {
+						parent = last_or_add(parent, key);
+					}
+					for value in item.into_values(&key)? {
+						parent.add_child(value);
+					}
+				}
+				Ok(table)
+			}
+			Item::Array(_) => unreachable!("arrays are split into their elements"),
+		}
+	}
+}
+
+struct Reader<'a> {
+	text: &'a str,
+	/// The byte we're at in the text
+	pos: usize,
+	line: usize,
+}
+
+impl<'a> Reader<'a> {
+	fn rest(&self) -> &'a str {
+		&self.text[self.pos..]
+	}
+
+	fn peek(&self) -> Option<char> {
+		self.rest().chars().next()
+	}
+
+	fn bump(&mut self) -> Option<char> {
+		let c = self.peek()?;
+		self.pos += c.len_utf8();
+		if c == '\n' {
+			self.line += 1;
+		}
+		Some(c)
+	}
+
+	fn eat(&mut self, c: char) -> bool {
+		if self.peek() == Some(c) {
+			self.bump();
+			true
+		} else {
+			false
+		}
+	}
+
+	/// Take `s` if the text continues with it. It can't have line breaks.
+	fn eat_str(&mut self, s: &str) -> bool {
+		if self.rest().starts_with(s) {
+			self.pos += s.len();
+			true
+		} else {
+			false
+		}
+	}
+
+	fn error(&self, message: &'static str) -> ImportError {
+		ImportError {
+			line: self.line,
+			kind: ImportErrorKind::Syntax(message),
+		}
+	}
+
+	fn expect(&mut self, expected: char, message: &'static str) -> Result<(), ImportError> {
+		match self.eat(expected) {
+			true => Ok(()),
+			false => Err(self.error(message)),
+		}
+	}
+
+	fn spaces(&mut self) {
+		while matches!(self.peek(), Some(' ' | '\t')) {
+			self.bump();
+		}
+	}
+
+	/// Whitespace, line breaks, and comments, like between array elements.
+	fn blanks(&mut self) {
+		loop {
+			match self.peek() {
+				Some(' ' | '\t' | '\r' | '\n') => {
+					self.bump();
+				}
+				Some('#') => {
+					self.comment();
+				}
+				_ => return,
+			}
+		}
+	}
+
+	fn newline(&mut self) -> Result<(), ImportError> {
+		if self.eat_str("\r\n") || self.eat('\n') {
+			Ok(())
+		} else {
+			Err(self.error("expected the end of the line"))
+		}
+	}
+
+	fn end_of_line(&mut self) -> Result<(), ImportError> {
+		match self.peek() {
+			None => Ok(()),
+			Some(_) => self.newline(),
+		}
+	}
+
+	/// The text of the comment we're at, without the `#`, up to the end of
+	/// the line.
+	fn comment(&mut self) -> &'a str {
+		self.bump();
+		let rest = self.rest();
+		let len = rest.find('\n').unwrap_or(rest.len());
+		let comment = &rest[..len];
+		self.pos += len;
+		comment.strip_suffix('\r').unwrap_or(comment)
+	}
+
+	/// The comment after something on a line, if there is one, and the end
+	/// of the line.
+	fn trailing_comment(&mut self) -> Result<Option<&'a str>, ImportError> {
+		self.spaces();
+		let comment = match self.peek() {
+			Some('#') => Some(self.comment()),
+			_ => None,
+		};
+		self.end_of_line()?;
+		Ok(comment)
+	}
+
+	/// A dotted key, and any spaces after it.
+	fn key(&mut self) -> Result<Vec<String>, ImportError> {
+		let mut keys = vec![];
+		loop {
+			let line = self.line;
+			let key = match self.peek() {
+				Some('"') => self.basic_string()?,
+				Some('\'') => self.literal_string()?,
+				_ => {
+					let start = self.pos;
+					while matches!(self.peek(), Some(c) if c.is_ascii_alphanumeric() || c == '_' || c == '-')
+					{
+						self.bump();
+					}
+					if start == self.pos {
+						return Err(self.error("expected a key"));
+					}
+					self.text[start..self.pos].to_owned()
+				}
+			};
+
+			check_key(&key, line)?;
+			keys.push(key);
+
+			self.spaces();
+			if !self.eat('.') {
+				return Ok(keys);
+			}
+			self.spaces();
+		}
+	}
+
+	fn value(&mut self) -> Result<Item, ImportError> {
+		let line = self.line;
+		let string = match self.peek() {
+			Some('"') if self.rest().starts_with("\"\"\"") => self.multiline_string("\"\"\"")?,
+			Some('\'') if self.rest().starts_with("'''") => self.multiline_string("'''")?,
+			Some('"') => self.basic_string()?,
+			Some('\'') => self.literal_string()?,
+			Some('[') => return self.array(),
+			Some('{') => return self.inline_table(),
+			_ => return Ok(Item::Scalar(Some(self.scalar()?))),
+		};
+
+		if string.contains(['\r', '\n']) {
+			return Err(ImportError {
+				line,
+				kind: ImportErrorKind::MultilineValue,
+			});
+		}
+		Ok(Item::Scalar(Some(string).filter(|s| !s.is_empty())))
+	}
+
+	fn array(&mut self) -> Result<Item, ImportError> {
+		self.bump();
+		let mut elements = vec![];
+
+		loop {
+			self.blanks();
+			if self.eat(']') {
+				break;
+			}
+
+			elements.push((self.line, self.value()?));
+
+			self.blanks();
+			if self.eat(']') {
+				break;
+			}
+			self.expect(',', "expected a comma or a closing bracket")?;
+		}
+
+		Ok(Item::Array(elements))
+	}
+
+	fn inline_table(&mut self) -> Result<Item, ImportError> {
+		self.bump();
+		let mut members = vec![];
+
+		self.spaces();
+		if self.eat('}') {
+			return Ok(Item::Table(members));
+		}
+
+		loop {
+			self.spaces();
+			let keys = self.key()?;
+			self.expect('=', "expected an = after the key")?;
+			self.spaces();
+			members.push((keys, self.value()?));
+
+			self.spaces();
+			if self.eat('}') {
+				return Ok(Item::Table(members));
+			}
+			self.expect(',', "expected a comma or a closing brace")?;
+		}
+	}
+
+	/// Numbers, booleans, dates, and times, which are all kept as they're
+	/// written.
+	fn scalar(&mut self) -> Result<String, ImportError> {
+		let start = self.pos;
+		self.word();
+
+		// Dates and times can be separated by a space instead of a T
+		let date = &self.text[start..self.pos];
+		let time_follows = self
+			.rest()
+			.strip_prefix(' ')
+			.is_some_and(|rest| rest.starts_with(|c: char| c.is_ascii_digit()));
+		if date.len() == 10 && date.as_bytes()[4] == b'-' && time_follows {
+			self.bump();
+			self.word();
+		}
+
+		let scalar = &self.text[start..self.pos];
+		let valid = matches!(scalar, "true" | "false" | "inf" | "nan")
+			|| scalar.starts_with(|c: char| c.is_ascii_digit() || c == '+' || c == '-');
+		if !valid {
+			return Err(self.error("expected a value"));
+		}
+
+		Ok(scalar.replace('_', ""))
+	}
+
+	fn word(&mut self) {
+		while matches!(self.peek(), Some(c) if c.is_ascii_alphanumeric() || matches!(c, '_' | '+' | '-' | '.' | ':'))
+		{
+			self.bump();
+		}
+	}
+
+	fn basic_string(&mut self) -> Result<String, ImportError> {
+		self.bump();
+		let mut string = String::new();
+
+		loop {
+			match self.bump() {
+				None | Some('\n') => return Err(self.error("expected a \" to close the string")),
+				Some('"') => return Ok(string),
+				Some('\\') => string.push(self.escape()?),
+				Some(c) => string.push(c),
+			}
+		}
+	}
+
+	fn literal_string(&mut self) -> Result<String, ImportError> {
+		self.bump();
+		let rest = self.rest();
+
+		match rest.find(['\'', '\n']) {
+			Some(len) if rest[len..].starts_with('\'') => {
+				self.pos += len + 1;
+				Ok(rest[..len].to_owned())
+			}
+			_ => Err(self.error("expected a ' to close the string")),
+		}
+	}
+
+	/// A string in triple quotes, which can go over more than one line. Only
+	/// `"""` strings have escapes.
+	fn multiline_string(&mut self, quotes: &'static str) -> Result<String, ImportError> {
+		self.eat_str(quotes);
+		// A line break right after the quotes isn't part of the string
+		let _ = self.newline();
+
+		let escapes = quotes == "\"\"\"";
+		let mut string = String::new();
+
+		loop {
+			if self.eat_str(quotes) {
+				return Ok(string);
+			}
+
+			match self.bump() {
+				None => {
+					return Err(self.error(match escapes {
+						true => "expected a \"\"\" to close the string",
+						false => "expected a ''' to close the string",
+					}))
+				}
+				Some('\\') if escapes => {
+					// A backslash at the end of a line trims up to the next
+					// thing that isn't whitespace
+					let trimmed = self.rest().trim_start_matches([' ', '\t']);
+					if trimmed.starts_with(['\r', '\n']) {
+						self.blanks_only();
+					} else {
+						string.push(self.escape()?);
+					}
+				}
+				Some(c) => string.push(c),
+			}
+		}
+	}
+
+	/// Whitespace and line breaks, but not comments.
+	fn blanks_only(&mut self) {
+		while matches!(self.peek(), Some(' ' | '\t' | '\r' | '\n')) {
+			self.bump();
+		}
+	}
+
+	fn escape(&mut self) -> Result<char, ImportError> {
+		let c = match self.bump() {
+			Some('b') => '\u{8}',
+			Some('t') => '\t',
+			Some('n') => '\n',
+			Some('f') => '\u{c}',
+			Some('r') => '\r',
+			Some('"') => '"',
+			Some('\\') => '\\',
+			Some('u') => return self.unicode(4),
+			Some('U') => return self.unicode(8),
+			_ => return Err(self.error("expected a valid escape")),
+		};
+		Ok(c)
+	}
+
+	fn unicode(&mut self, digits: usize) -> Result<char, ImportError> {
+		let hex = self.rest().get(..digits).unwrap_or_default();
+		let code = match hex.chars().all(|c| c.is_ascii_hexdigit()) {
+			true => u32::from_str_radix(hex, 16).ok(),
+			false => None,
+		};
+
+		match code.and_then(char::from_u32) {
+			Some(c) => {
+				self.pos += digits;
+				Ok(c)
+			}
+			None => Err(self.error("expected a valid unicode escape")),
+		}
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	#[test]
+	fn tables_and_arrays() {
+		let toml = r#"# top
+name = "a\tb\u00e9" # inline
+literal = 'C:\path'
+empty = ""
+big = 1_000_000
+when = 1979-05-27 07:32:00Z
+multi = """\
+	joined \
+	up"""
+
+[a.b]
+c.d = true
+inline = { x = 1, y.z = [2, 3], e = [] }
+
+[a]
+[[list]]
+n = 1
+[[list]]
+n = 2
+[list.sub]
+m = [
+	{ k = "v" }, # dropped
+	'w',
+]
+"#;
+		let conf = Confindent::from_toml(toml).unwrap();
+
+		assert_eq!(
+			conf.to_string(),
+			"# top\n# inline\nname a\tb\u{e9}\nliteral C:\\path\nempty\nbig 1000000\nwhen 1979-05-27 07:32:00Z\nmulti joined up\n\na\n\tb\n\t\tc\n\t\t\td true\n\t\tinline\n\t\t\tx 1\n\t\t\ty\n\t\t\t\tz 2\n\t\t\t\tz 3\n\nlist\n\tn 1\nlist\n\tn 2\n\tsub\n\t\tm\n\t\t\tk v\n\t\tm w\n"
+		);
+	}
+
+	#[test]
+	fn unmappable_toml() {
+		let err = |toml| Confindent::from_toml(toml).unwrap_err();
+
+		assert_eq!(
+			err("a = 1\nb = \"\"\"\nx\ny\"\"\""),
+			ImportError {
+				line: 1,
+				kind: ImportErrorKind::MultilineValue
+			}
+		);
+		assert_eq!(
+			err("a = [\n\t1,\n\t[2],\n]"),
+			ImportError {
+				line: 2,
+				kind: ImportErrorKind::NestedArray
+			}
+		);
+		assert_eq!(
+			err("\"a b\" = 1").kind,
+			ImportErrorKind::InvalidKey("a b".into())
+		);
+		assert_eq!(
+			err("\n\na = nope"),
+			ImportError {
+				line: 2,
+				kind: ImportErrorKind::Syntax("expected a value")
+			}
+		);
+		assert_eq!(
+			err("[a\nb = 1").kind,
+			ImportErrorKind::Syntax("expected a ] to close the table")
+		);
+		assert_eq!(
+			err("a = 1 b = 2").kind,
+			ImportErrorKind::Syntax("expected the end of the line")
+		);
+	}
+}
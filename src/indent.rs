@@ -59,6 +59,71 @@ impl Indent {
 			},
 		}
 	}
+
+	/// How many tabs or spaces this is.
+	pub(crate) fn count(&self) -> usize {
+		match *self {
+			Indent::Empty => 0,
+			Indent::Tabs { count, .. } | Indent::Spaces { count, .. } => count,
+		}
+	}
+
+	/// How far across the line this reaches, with tabs `tab_width` wide.
+	pub(crate) fn columns(&self, tab_width: usize) -> usize {
+		match *self {
+			Indent::Empty => 0,
+			Indent::Tabs { count, .. } => count * tab_width,
+			Indent::Spaces { count, .. } => count,
+		}
+	}
+
+	/// Read whitespace that mixes tabs and spaces as the spaces it takes to
+	/// reach the same column. A tab goes to the next multiple of `tab_width`.
+	pub(crate) fn from_mixed(s: &str, tab_width: usize) -> Indent {
+		let column = s.chars().fold(0, |column, ch| match ch {
+			'\t' => (column / tab_width + 1) * tab_width,
+			_ => column + 1,
+		});
+
+		match column {
+			0 => Indent::Empty,
+			count => Indent::Spaces {
+				count,
+				delta: count,
+			},
+		}
+	}
+
+	/// Like [delta_from](Indent::delta_from), but by the columns the indents
+	/// reach, so tabs and spaces can be measured against each other.
+	pub(crate) fn delta_by_column(&mut self, other: &Indent, tab_width: usize) {
+		let diff = self.columns(tab_width).abs_diff(other.columns(tab_width));
+
+		match self {
+			Indent::Empty => (),
+			Indent::Tabs { delta, .. } => *delta = diff.div_ceil(tab_width),
+			Indent::Spaces { delta, .. } => *delta = diff,
+		}
+	}
+
+	/// The indent of a child one level deeper than this. Tabs go one tab
+	/// deeper and spaces go as deep as they last did.
+	pub(crate) fn child(&self) -> Indent {
+		match *self {
+			Indent::Empty => Indent::Tabs { count: 1, delta: 1 },
+			Indent::Tabs { count, .. } => Indent::Tabs {
+				count: count + 1,
+				delta: 1,
+			},
+			Indent::Spaces { count, delta } => {
+				let delta = if delta == 0 { count } else { delta };
+				Indent::Spaces {
+					count: count + delta,
+					delta,
+				}
+			}
+		}
+	}
 }
 
 impl Add<usize> for Indent {
@@ -0,0 +1,331 @@
+use core::fmt;
+use std::{borrow::Cow, collections::HashMap, slice, sync::OnceLock};
+
+use crate::{line::Line, Value};
+
+/// How keys are compared when looking them up. However they're compared,
+/// keys are always written the way they were.
+///
+/// # Example
+///
+/// ```rust
+/// use confindent::{Confindent, KeyCase};
+///
+/// let mut conf: Confindent = "Host example.com\n\tPort 22\n".parse().unwrap();
+/// assert_eq!(conf.get("host/port"), None);
+///
+/// conf.set_key_case(KeyCase::IgnoreAscii);
+/// assert_eq!(conf.get("host/port"), Some("22"));
+/// assert_eq!(conf.get("HOST/Port"), Some("22"));
+/// assert_eq!(conf.to_string(), "Host example.com\n\tPort 22\n");
+/// ```
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum KeyCase {
+	/// Keys have to be exactly the same.
+	#[default]
+	Sensitive,
+	/// ASCII letters match whatever their case, so `port` finds `PORT`.
+	IgnoreAscii,
+	/// Any letters match whatever their case, so `größe` finds `GRÖßE`.
+	/// Keys are compared by their lowercase, which isn't quite full case
+	/// folding, so `ß` still doesn't find `SS`.
+	IgnoreUnicode,
+}
+
+impl KeyCase {
+	/// Whether two keys are the same.
+	pub fn matches(self, a: &str, b: &str) -> bool {
+		match self {
+			KeyCase::Sensitive => a == b,
+			KeyCase::IgnoreAscii => a.eq_ignore_ascii_case(b),
+			// Lowercasing a whole string is the same as a character at a
+			// time except for Σ, which is ς at the end of a word. Only keys
+			// with one are folded like the index does, so the rest don't
+			// allocate
+			KeyCase::IgnoreUnicode if a.contains('Σ') || b.contains('Σ') => {
+				self.fold(a) == self.fold(b)
+			}
+			KeyCase::IgnoreUnicode => a
+				.chars()
+				.flat_map(char::to_lowercase)
+				.eq(b.chars().flat_map(char::to_lowercase)),
+		}
+	}
+
+	/// The key as it's compared, so keys that match fold to the same thing.
+	fn fold(self, key: &str) -> Cow<'_, str> {
+		match self {
+			KeyCase::Sensitive => Cow::Borrowed(key),
+			KeyCase::IgnoreAscii => Cow::Owned(key.to_ascii_lowercase()),
+			KeyCase::IgnoreUnicode => Cow::Owned(key.to_lowercase()),
+		}
+	}
+}
+
+/// Where each key is in a list of lines, so that looking up a child doesn't
+/// mean looking at every line.
+///
+/// The index is built the first time it's needed, and only if there are
+/// enough lines to make it worthwhile. Anything that adds or removes lines
+/// has to keep it up to date.
+///
+/// It also knows how keys are compared, because the positions are kept by
+/// the key as it's compared.
+#[derive(Default)]
+pub(crate) struct KeyIndex {
+	positions: OnceLock<HashMap<String, Vec<usize>>>,
+	case: KeyCase,
+}
+
+impl KeyIndex {
+	/// Below this many lines it's faster to just look.
+	const MIN_LINES: usize = 16;
+
+	/// The positions of every value with the key, in order, or None if
+	/// there are too few lines to bother indexing.
+	pub(crate) fn get<'a>(&'a self, lines: &[Line], key: &str) -> Option<&'a [usize]> {
+		if lines.len() < Self::MIN_LINES {
+			return None;
+		}
+
+		let positions = self.positions.get_or_init(|| {
+			let mut positions: HashMap<String, Vec<usize>> = HashMap::new();
+			for (idx, line) in lines.iter().enumerate() {
+				if let Line::Value(value) = line {
+					let key = self.case.fold(&value.key).into_owned();
+					positions.entry(key).or_default().push(idx);
+				}
+			}
+			positions
+		});
+
+		let key = self.case.fold(key);
+		Some(
+			positions
+				.get(key.as_ref())
+				.map(Vec::as_slice)
+				.unwrap_or_default(),
+		)
+	}
+
+	pub(crate) fn case(&self) -> KeyCase {
+		self.case
+	}
+
+	/// Compare keys differently from now on.
+	pub(crate) fn set_case(&mut self, case: KeyCase) {
+		if self.case != case {
+			self.case = case;
+			self.clear();
+		}
+	}
+
+	/// Whether the key of a line is `key`.
+	pub(crate) fn matches(&self, value: &Value, key: &str) -> bool {
+		self.case.matches(&value.key, key)
+	}
+
+	/// Lines were removed, or moved around, so the positions are wrong.
+	pub(crate) fn clear(&mut self) {
+		self.positions = OnceLock::new();
+	}
+
+	/// A value with the key was added to the end of the lines.
+	pub(crate) fn pushed(&mut self, key: &str, position: usize) {
+		if let Some(positions) = self.positions.get_mut() {
+			let key = self.case.fold(key).into_owned();
+			positions.entry(key).or_default().push(position);
+		}
+	}
+}
+
+// The index is a cache. It doesn't change what a value is, so it doesn't
+// get cloned or compared and isn't worth printing. How keys are compared
+// is kept, though.
+impl Clone for KeyIndex {
+	fn clone(&self) -> Self {
+		Self {
+			positions: OnceLock::new(),
+			case: self.case,
+		}
+	}
+}
+
+impl PartialEq for KeyIndex {
+	fn eq(&self, _: &Self) -> bool {
+		true
+	}
+}
+
+impl fmt::Debug for KeyIndex {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.write_str("KeyIndex")
+	}
+}
+
+/// An iterator over the children with a key. See [Value::children_iter].
+pub struct Children<'a, S: AsRef<str>> {
+	inner: ChildrenInner<'a, S>,
+}
+
+enum ChildrenInner<'a, S> {
+	Indexed {
+		lines: &'a [Line],
+		positions: slice::Iter<'a, usize>,
+	},
+	Scan {
+		lines: slice::Iter<'a, Line>,
+		key: S,
+		case: KeyCase,
+	},
+}
+
+impl<'a, S: AsRef<str>> Children<'a, S> {
+	pub(crate) fn new(lines: &'a [Line], index: &'a KeyIndex, key: S) -> Self {
+		let inner = match index.get(lines, key.as_ref()) {
+			Some(positions) => ChildrenInner::Indexed {
+				lines,
+				positions: positions.iter(),
+			},
+			None => ChildrenInner::Scan {
+				lines: lines.iter(),
+				key,
+				case: index.case,
+			},
+		};
+
+		Self { inner }
+	}
+}
+
+impl<'a, S: AsRef<str>> Iterator for Children<'a, S> {
+	type Item = &'a Value;
+
+	fn next(&mut self) -> Option<Self::Item> {
+		match &mut self.inner {
+			ChildrenInner::Indexed { lines, positions } => {
+				positions.next().and_then(|idx| match &lines[*idx] {
+					Line::Value(value) => Some(value),
+					_ => None,
+				})
+			}
+			ChildrenInner::Scan { lines, key, case } => lines.find_map(|line| match line {
+				Line::Value(value) if case.matches(&value.key, key.as_ref()) => Some(value),
+				_ => None,
+			}),
+		}
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use crate::{index::KeyIndex, Confindent, KeyCase, Value};
+
+	fn wide() -> Confindent {
+		let mut conf = String::from("Root\n");
+		for i in 0..100 {
+			conf.push_str(&format!("\tKey{} {i}\n\t# Comment\n", i % 10));
+		}
+		conf.parse().unwrap()
+	}
+
+	#[test]
+	fn indexed_lookup_keeps_order() {
+		let conf = wide();
+		let root = conf.child("Root").unwrap();
+
+		let values: Vec<&str> = root
+			.children_iter("Key3")
+			.filter_map(|v| v.value())
+			.collect();
+		assert_eq!(
+			values,
+			["3", "13", "23", "33", "43", "53", "63", "73", "83", "93"]
+		);
+
+		assert_eq!(root.child_value("Key9"), Some("9"));
+		assert!(!root.has_child("Missing"));
+	}
+
+	#[test]
+	fn index_follows_added_children() {
+		let mut conf = wide();
+		let root = conf.child_mut("Root").unwrap();
+
+		// Build the index and then change things
+		assert!(root.has_child("Key0"));
+		root.add_child(Value::new("Added", Some("yes")));
+		root.add_child(Value::new("Key0", Some("last")));
+
+		assert_eq!(root.child_value("Added"), Some("yes"));
+		assert_eq!(root.children("Key0").last().unwrap().value(), Some("last"));
+		assert_eq!(root.child_mut("Added").unwrap().value(), Some("yes"));
+	}
+
+	#[test]
+	fn case_insensitive_keys() {
+		let mut conf = wide();
+		conf.add_child(Value::new("Small", None::<&str>));
+		conf.set_key_case(KeyCase::IgnoreAscii);
+
+		// Looked up through the index and without one
+		let root = conf.child("ROOT").unwrap();
+		assert_eq!(root.children("key3").len(), 10);
+		assert_eq!(conf.get("root/KEY9"), Some("9"));
+		assert!(conf.has_child("small"));
+
+		conf.set("small/port", Some(22));
+		conf.set("SMALL/Port", Some(2222));
+		assert_eq!(conf.get("Small/PORT"), Some("2222"));
+		assert!(conf.to_string().ends_with("Small\n\tport 2222\n"));
+
+		let added = conf.child_mut("Small").unwrap();
+		added.add_child(Value::new("Nested", None::<&str>));
+		assert_eq!(
+			added.child("nested").unwrap().key_case(),
+			KeyCase::IgnoreAscii
+		);
+
+		assert!(conf.remove("ROOT").is_some());
+		assert!(!conf.has_child("Root"));
+
+		conf.set_key_case(KeyCase::Sensitive);
+		assert_eq!(conf.get("small/port"), None);
+	}
+
+	#[test]
+	fn unicode_case() {
+		let mut conf: Confindent = "Größe 1\nÄrger 2\n".parse().unwrap();
+		conf.set_key_case(KeyCase::IgnoreAscii);
+		assert_eq!(conf.get("ärger"), None);
+
+		conf.set_key_case(KeyCase::IgnoreUnicode);
+		assert_eq!(conf.get("ärger"), Some("2"));
+		assert_eq!(conf.get("GRÖßE"), Some("1"));
+		assert_eq!(conf.get("GRÖSSE"), None);
+	}
+
+	#[test]
+	fn final_sigma() {
+		let narrow = "ΟΔΟΣ 1\n".to_owned();
+		let wide = narrow.clone() + &"Other 2\n".repeat(KeyIndex::MIN_LINES);
+
+		// Found the same way by looking, and through the index
+		for text in [narrow, wide] {
+			let mut conf: Confindent = text.parse().unwrap();
+			conf.set_key_case(KeyCase::IgnoreUnicode);
+			assert_eq!(conf.get("οδος"), Some("1"));
+			assert_eq!(conf.get("Οδος"), Some("1"));
+		}
+
+		let keys = [
+			"ΟΔΟΣ", "οδος", "οδοσ", "ΣΑ", "σα", "İ", "i̇", "i", "Größe", "GRÖSSE",
+		];
+		for a in keys {
+			for b in keys {
+				let folded = a.to_lowercase() == b.to_lowercase();
+				assert_eq!(KeyCase::IgnoreUnicode.matches(a, b), folded, "{a} {b}");
+			}
+		}
+	}
+}
@@ -0,0 +1,259 @@
+//! `Inherit` lines, for blocks that are mostly like another one.
+
+use core::fmt;
+use std::error::Error as StdError;
+
+use crate::{index::KeyIndex, line::Line, typed, Confindent, Value};
+
+/// An `Inherit` line that couldn't be followed.
+#[derive(Clone, Debug, PartialEq)]
+pub struct InheritError {
+	/// The `/` separated path to the `Inherit` line
+	pub path: String,
+	/// The line it's on, starting from zero
+	pub line: Option<usize>,
+	pub kind: InheritErrorKind,
+}
+
+/// What was wrong with the `Inherit` line.
+#[derive(Clone, Debug, PartialEq)]
+pub enum InheritErrorKind {
+	/// There was no path after `Inherit`
+	NoPath,
+	/// Nothing is at the path
+	NotFound(String),
+	/// Following the paths came back around to a block that was already
+	/// being inherited. They're in the order they were followed, and the
+	/// last is the one that was seen before.
+	Cycle(Vec<String>),
+}
+
+impl Value {
+	/// Whether this value was copied from another block by an `Inherit`
+	/// line, rather than written where it is. See [Confindent::inherit].
+	pub fn is_inherited(&self) -> bool {
+		self.inherited
+	}
+
+	fn set_inherited(&mut self) {
+		self.inherited = true;
+		for child in self.values_mut() {
+			child.set_inherited();
+		}
+	}
+}
+
+impl Confindent {
+	/// The document with its `Inherit` lines followed.
+	///
+	/// `Inherit`, or `Extends`, is followed by the `/` separated path of
+	/// another value, starting from the top of the document. The line is
+	/// replaced by copies of that value's children, except those with the
+	/// same key as one of the block's own children. Those win, children and
+	/// all. The value being inherited from can inherit too, so long as it
+	/// doesn't end up inheriting from itself.
+	///
+	/// Copied values say they're [inherited](Value::is_inherited), and keep
+	/// the [line](Value::line) they were copied from.
+	///
+	/// If you're using `Match` blocks too, [evaluate](Confindent::evaluate)
+	/// them first so only the ones that matched are inherited.
+	///
+	/// # Example
+	///
+	/// ```rust
+	/// use confindent::Confindent;
+	///
+	/// let conf: Confindent = "Defaults\n\tPort 22\n\tUser root\nServer a\n\tInherit Defaults\n\tUser gen"
+	/// 	.parse()
+	/// 	.unwrap();
+	/// let conf = conf.inherit().unwrap();
+	///
+	/// let server = conf.child("Server").unwrap();
+	/// assert_eq!(server.child_value("Port"), Some("22"));
+	/// assert!(server.child("Port").unwrap().is_inherited());
+	/// assert_eq!(server.child_value("User"), Some("gen"));
+	/// assert!(!server.child("User").unwrap().is_inherited());
+	/// ```
+	pub fn inherit(&self) -> Result<Confindent, InheritError> {
+		let mut conf = self.clone();
+		let mut following = vec![];
+		conf.children = inherit(self, &self.children, &self.index, "", &mut following)?;
+		conf.index.clear();
+		Ok(conf)
+	}
+}
+
+/// Follow the `Inherit` lines in the lines, and in their children.
+/// `following` is every value whose children we're in the middle of, and
+/// its path, so we know when we've come back to one.
+fn inherit<'a>(
+	conf: &'a Confindent,
+	lines: &'a [Line],
+	index: &KeyIndex,
+	parent: &str,
+	following: &mut Vec<(&'a Value, String)>,
+) -> Result<Vec<Line>, InheritError> {
+	let is_inherit =
+		|value: &Value| index.matches(value, "Inherit") || index.matches(value, "Extends");
+	let own: Vec<&str> = lines
+		.iter()
+		.filter_map(|line| match line {
+			Line::Value(value) if !is_inherit(value) => Some(value.key.as_str()),
+			_ => None,
+		})
+		.collect();
+
+	let mut inherited = Vec::with_capacity(lines.len());
+	for line in lines {
+		let Line::Value(value) = line else {
+			inherited.push(line.clone());
+			continue;
+		};
+
+		let path = match parent {
+			"" => value.key.clone(),
+			parent => format!("{parent}/{}", value.key),
+		};
+
+		if !is_inherit(value) {
+			following.push((value, path.clone()));
+			let children = inherit(conf, &value.children, &value.index, &path, following)?;
+			following.pop();
+
+			let mut value = value.clone();
+			value.children = children;
+			value.index.clear();
+			inherited.push(Line::Value(value));
+			continue;
+		}
+
+		let error = |kind| InheritError {
+			path: path.clone(),
+			line: value.line,
+			kind,
+		};
+
+		let target_path = value
+			.value
+			.as_deref()
+			.map(str::trim)
+			.filter(|target| !target.is_empty())
+			.ok_or_else(|| error(InheritErrorKind::NoPath))?;
+		let target = typed::walk(&conf.children, &conf.index, target_path, '/')
+			.ok_or_else(|| error(InheritErrorKind::NotFound(target_path.to_owned())))?;
+
+		if let Some(start) = following
+			.iter()
+			.position(|(other, _)| std::ptr::eq(*other, target))
+		{
+			let mut cycle: Vec<String> = following[start..]
+				.iter()
+				.map(|(_, path)| path.clone())
+				.collect();
+			cycle.push(target_path.to_owned());
+			return Err(error(InheritErrorKind::Cycle(cycle)));
+		}
+
+		following.push((target, target_path.to_owned()));
+		let copied = inherit(
+			conf,
+			&target.children,
+			&target.index,
+			target_path,
+			following,
+		)?;
+		following.pop();
+
+		for line in copied {
+			let Line::Value(mut copy) = line else {
+				continue;
+			};
+			if own.iter().any(|key| index.matches(&copy, key)) {
+				continue;
+			}
+
+			copy.reindent(value.indent);
+			copy.set_inherited();
+			inherited.push(Line::Value(copy));
+		}
+	}
+
+	Ok(inherited)
+}
+
+impl StdError for InheritError {}
+impl fmt::Display for InheritError {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self.line {
+			None => write!(f, "{}: ", self.path)?,
+			Some(line) => write!(f, "{} on line {line}: ", self.path)?,
+		}
+
+		match &self.kind {
+			InheritErrorKind::NoPath => write!(f, "nothing to inherit from"),
+			InheritErrorKind::NotFound(target) => {
+				write!(f, "can't inherit {target}, it isn't there")
+			}
+			InheritErrorKind::Cycle(cycle) => {
+				write!(
+					f,
+					"inheriting goes around in a circle: {}",
+					cycle.join(" -> ")
+				)
+			}
+		}
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	#[test]
+	fn chains_and_overrides() {
+		let text = "Base\n\tPort 22\n\tTls\n\t\tCert base.pem\nWeb\n\t# the web ones\n\tExtends Base\n\tUser www\nServer a\n\tInherit Web\n\tPort 2222\nServer b\n\tInherit Server\n\tUser gen";
+		let conf: Confindent = text.parse().unwrap();
+		let inherited = conf.inherit().unwrap();
+
+		assert_eq!(
+			inherited.to_string(),
+			"Base\n\tPort 22\n\tTls\n\t\tCert base.pem\nWeb\n\t# the web ones\n\tPort 22\n\tTls\n\t\tCert base.pem\n\tUser www\nServer a\n\tTls\n\t\tCert base.pem\n\tUser www\n\tPort 2222\nServer b\n\tTls\n\t\tCert base.pem\n\tPort 2222\n\tUser gen"
+		);
+
+		let b = inherited.children("Server")[1];
+		assert!(b.child("Port").unwrap().is_inherited());
+		assert!(b.get("Tls/Cert").is_some());
+		assert!(b
+			.child("Tls")
+			.unwrap()
+			.child("Cert")
+			.unwrap()
+			.is_inherited());
+		assert!(!b.child("User").unwrap().is_inherited());
+		// Copies remember where they came from
+		assert_eq!(b.child("Tls").unwrap().line(), Some(2));
+
+		// The document itself doesn't change
+		assert_eq!(conf.to_string(), text);
+	}
+
+	#[test]
+	fn errors() {
+		let err = |text: &str| text.parse::<Confindent>().unwrap().inherit().unwrap_err();
+
+		assert_eq!(
+			err("A\n\tInherit B\nB\n\tInherit C\nC\n\tInherit A").kind,
+			InheritErrorKind::Cycle(vec!["A".into(), "B".into(), "C".into(), "A".into()])
+		);
+		assert_eq!(
+			err("A\n\tInherit A").to_string(),
+			"A/Inherit on line 1: inheriting goes around in a circle: A -> A"
+		);
+		assert_eq!(
+			err("A\n\tB\n\t\tInherit Nope").to_string(),
+			"A/B/Inherit on line 2: can't inherit Nope, it isn't there"
+		);
+		assert_eq!(err("A\n\tInherit").kind, InheritErrorKind::NoPath);
+	}
+}
@@ -0,0 +1,762 @@
+//! Converting documents to and from JSON.
+
+use core::fmt;
+use std::{collections::HashMap, error::Error as StdError, fmt::Write};
+
+use crate::{secret::REDACTED, Confindent, Value};
+
+/// How deep arrays and objects can be nested before we give up
+const MAX_DEPTH: usize = 256;
+
+/// The error returned when JSON can't be turned into a [Confindent].
+#[derive(Clone, Debug, PartialEq)]
+pub struct JsonError {
+	/// The line of the JSON the problem is on, starting from zero
+	pub line: usize,
+	/// The character in the line the problem starts at, from zero
+	pub column: usize,
+	pub kind: JsonErrorKind,
+}
+
+/// What was wrong with the JSON.
+#[derive(Clone, Debug, PartialEq)]
+pub enum JsonErrorKind {
+	/// It isn't valid JSON. The message says what was expected.
+	Syntax(&'static str),
+	/// The JSON isn't an object, and a document has to be
+	NotAnObject,
+	/// An array in an array, which would need a key without a name
+	NestedArray,
+	/// `$value` is an array or an object, but values can't have children
+	/// of their own
+	InvalidOwnValue,
+	/// A key that can't be written, like one that's empty or has whitespace
+	InvalidKey(String),
+	/// A key starting with a single `$` that isn't `$value`
+	ReservedKey(String),
+	/// A string with a line break in it, because values are one line
+	MultilineValue,
+}
+
+/// The member holding the value of something with children
+pub(crate) const VALUE_MEMBER: &str = "$value";
+
+impl Confindent {
+	/// Convert the document to pretty-printed JSON.
+	///
+	/// The document is an object, and so is every value with children. A
+	/// key that's there once is a member, and a key that's there more than
+	/// once is an array of them in order. A value without children is its
+	/// value as a string, or `null` if it doesn't have one. A value with
+	/// children keeps its own value in the reserved `$value` member. Keys
+	/// starting with a `$` get another one in front so they can't be
+	/// mistaken for it.
+	///
+	/// Comments, blank lines, and how things were indented aren't kept.
+	///
+	/// # Example
+	///
+	/// ```rust
+	/// use confindent::Confindent;
+	///
+	/// let conf: Confindent = "Host example.com\n\tPort 22\nAlias a\nAlias b".parse().unwrap();
+	///
+	/// assert_eq!(
+	/// 	conf.to_json(),
+	/// 	r#"{
+	/// 	"Host": {
+	/// 		"$value": "example.com",
+	/// 		"Port": "22"
+	/// 	},
+	/// 	"Alias": [
+	/// 		"a",
+	/// 		"b"
+	/// 	]
+	/// }"#
+	/// );
+	/// ```
+	pub fn to_json(&self) -> String {
+		let mut json = String::new();
+		write_object(&mut json, None, self.values(), 0);
+		json
+	}
+}
+
+impl Confindent {
+	/// Build a document from JSON laid out like [to_json](Confindent::to_json)
+	/// writes it.
+	///
+	/// Numbers are kept as they were written, `true` and `false` become
+	/// values, and `null` is a key without a value. Empty arrays and
+	/// objects have nothing to become, so keys with them are left out, and
+	/// an empty string is the same as no value at all.
+	///
+	/// Some JSON can't be a document, like arrays in arrays or strings with
+	/// line breaks, and is an error. See [JsonErrorKind] for all of them.
+	///
+	/// # Example
+	///
+	/// ```rust
+	/// use confindent::Confindent;
+	///
+	/// let json = r#"{"Host": [{"$value": "a", "Port": 22}, "b"], "Debug": true}"#;
+	/// let conf = Confindent::from_json(json).unwrap();
+	///
+	/// assert_eq!(conf.to_string(), "Host a\n\tPort 22\nHost b\nDebug true\n");
+	/// ```
+	pub fn from_json(json: &str) -> Result<Self, JsonError> {
+		let mut reader = Reader {
+			chars: json.char_indices().peekable(),
+			text: json,
+			line: 0,
+			line_start: 0,
+			depth: 0,
+		};
+
+		let root = reader.document()?;
+		let members = match root.json {
+			Json::Object(members) => members,
+			_ => return Err(root.at.error(JsonErrorKind::NotAnObject)),
+		};
+
+		let mut conf = Confindent::new();
+		for member in members {
+			for value in member.into_values()? {
+				conf.add_child(value);
+			}
+		}
+
+		Ok(conf)
+	}
+}
+
+/// Where something is in the JSON.
+#[derive(Clone, Copy, Debug)]
+struct At {
+	line: usize,
+	column: usize,
+}
+
+impl At {
+	fn error(self, kind: JsonErrorKind) -> JsonError {
+		JsonError {
+			line: self.line,
+			column: self.column,
+			kind,
+		}
+	}
+}
+
+struct Node<'a> {
+	at: At,
+	json: Json<'a>,
+}
+
+enum Json<'a> {
+	Null,
+	Bool(bool),
+	Number(&'a str),
+	String(String),
+	Array(Vec<Node<'a>>),
+	Object(Vec<Member<'a>>),
+}
+
+struct Member<'a> {
+	key: String,
+	at: At,
+	node: Node<'a>,
+}
+
+impl Member<'_> {
+	/// The values this member stands for. Arrays are one for each element.
+	fn into_values(self) -> Result<Vec<Value>, JsonError> {
+		let key = match self.key.strip_prefix('$') {
+			Some(rest) if rest.starts_with('$') => rest.to_owned(),
+			Some(_) => return Err(self.at.error(JsonErrorKind::ReservedKey(self.key))),
+			None => self.key,
+		};
+
+		let invalid =
+			key.is_empty() || key.starts_with('#') || key.chars().any(|c| c.is_whitespace());
+		if invalid {
+			return Err(self.at.error(JsonErrorKind::InvalidKey(key)));
+		}
+
+		match self.node.json {
+			Json::Array(elements) => elements
+				.into_iter()
+				.map(|element| match element.json {
+					Json::Array(_) => Err(element.at.error(JsonErrorKind::NestedArray)),
+					_ => element.into_value(&key),
+				})
+				.collect(),
+			Json::Object(members) if members.is_empty() => Ok(vec![]),
+			_ => Ok(vec![self.node.into_value(&key)?]),
+		}
+	}
+}
+
+impl Node<'_> {
+	fn into_value(self, key: &str) -> Result<Value, JsonError> {
+		let members = match self.json {
+			Json::Object(members) => members,
+			scalar => return Ok(Value::new(key, Node::scalar(scalar, self.at)?)),
+		};
+
+		let mut value = Value::new(key, None::<&str>);
+		for member in members {
+			if member.key == VALUE_MEMBER {
+				let own = Node::scalar(member.node.json, member.node.at)?;
+				value.set_value(own);
+				continue;
+			}
+
+			for child in member.into_values()? {
+				value.add_child(child);
+			}
+		}
+
+		Ok(value)
+	}
+
+	/// The value of anything that isn't an array or an object.
+	fn scalar(json: Json, at: At) -> Result<Option<String>, JsonError> {
+		match json {
+			Json::Null => Ok(None),
+			Json::Bool(b) => Ok(Some(b.to_string())),
+			Json::Number(number) => Ok(Some(number.to_owned())),
+			Json::String(s) if s.contains(['\n', '\r']) => {
+				Err(at.error(JsonErrorKind::MultilineValue))
+			}
+			Json::String(s) if s.is_empty() => Ok(None),
+			Json::String(s) => Ok(Some(s)),
+			Json::Array(_) | Json::Object(_) => Err(at.error(JsonErrorKind::InvalidOwnValue)),
+		}
+	}
+}
+
+/// Reads JSON, keeping track of where it is for errors.
+struct Reader<'a> {
+	chars: std::iter::Peekable<std::str::CharIndices<'a>>,
+	text: &'a str,
+	line: usize,
+	/// The byte the current line starts at
+	line_start: usize,
+	depth: usize,
+}
+
+impl<'a> Reader<'a> {
+	fn at(&mut self) -> At {
+		let offset = self.offset();
+		At {
+			line: self.line,
+			column: self.text[self.line_start..offset].chars().count(),
+		}
+	}
+
+	fn offset(&mut self) -> usize {
+		self.chars
+			.peek()
+			.map(|(idx, _)| *idx)
+			.unwrap_or(self.text.len())
+	}
+
+	fn error(&mut self, message: &'static str) -> JsonError {
+		self.at().error(JsonErrorKind::Syntax(message))
+	}
+
+	fn next(&mut self) -> Option<char> {
+		let (idx, c) = self.chars.next()?;
+		if c == '\n' {
+			self.line += 1;
+			self.line_start = idx + 1;
+		}
+		Some(c)
+	}
+
+	fn peek(&mut self) -> Option<char> {
+		self.chars.peek().map(|(_, c)| *c)
+	}
+
+	fn skip_whitespace(&mut self) {
+		while matches!(self.peek(), Some(' ' | '\t' | '\n' | '\r')) {
+			self.next();
+		}
+	}
+
+	fn expect(&mut self, expected: char, message: &'static str) -> Result<(), JsonError> {
+		self.skip_whitespace();
+		match self.peek() {
+			Some(c) if c == expected => {
+				self.next();
+				Ok(())
+			}
+			_ => Err(self.error(message)),
+		}
+	}
+
+	fn document(&mut self) -> Result<Node<'a>, JsonError> {
+		let node = self.value()?;
+
+		self.skip_whitespace();
+		match self.peek() {
+			None => Ok(node),
+			Some(_) => Err(self.error("expected the end of the document")),
+		}
+	}
+
+	fn value(&mut self) -> Result<Node<'a>, JsonError> {
+		self.skip_whitespace();
+		let at = self.at();
+
+		let json = match self.peek() {
+			Some('{') => self.nested(Reader::object)?,
+			Some('[') => self.nested(Reader::array)?,
+			Some('"') => Json::String(self.string()?),
+			Some('-' | '0'..='9') => Json::Number(self.number()?),
+			Some('t') => self.word("true", Json::Bool(true))?,
+			Some('f') => self.word("false", Json::Bool(false))?,
+			Some('n') => self.word("null", Json::Null)?,
+			_ => return Err(self.error("expected a value")),
+		};
+
+		Ok(Node { at, json })
+	}
+
+	fn nested<F>(&mut self, f: F) -> Result<Json<'a>, JsonError>
+	where
+		F: FnOnce(&mut Self) -> Result<Json<'a>, JsonError>,
+	{
+		if self.depth == MAX_DEPTH {
+			return Err(self.error("nothing nested so deeply"));
+		}
+
+		self.depth += 1;
+		let json = f(self);
+		self.depth -= 1;
+		json
+	}
+
+	fn object(&mut self) -> Result<Json<'a>, JsonError> {
+		self.next();
+		let mut members = vec![];
+
+		self.skip_whitespace();
+		if self.peek() == Some('}') {
+			self.next();
+			return Ok(Json::Object(members));
+		}
+
+		loop {
+			self.skip_whitespace();
+			let at = self.at();
+			if self.peek() != Some('"') {
+				return Err(self.error("expected a key"));
+			}
+			let key = self.string()?;
+
+			self.expect(':', "expected a colon")?;
+			let node = self.value()?;
+			members.push(Member { key, at, node });
+
+			self.skip_whitespace();
+			match self.next() {
+				Some(',') => continue,
+				Some('}') => return Ok(Json::Object(members)),
+				_ => return Err(self.error("expected a comma or a closing brace")),
+			}
+		}
+	}
+
+	fn array(&mut self) -> Result<Json<'a>, JsonError> {
+		self.next();
+		let mut elements = vec![];
+
+		self.skip_whitespace();
+		if self.peek() == Some(']') {
+			self.next();
+			return Ok(Json::Array(elements));
+		}
+
+		loop {
+			elements.push(self.value()?);
+
+			self.skip_whitespace();
+			match self.next() {
+				Some(',') => continue,
+				Some(']') => return Ok(Json::Array(elements)),
+				_ => return Err(self.error("expected a comma or a closing bracket")),
+			}
+		}
+	}
+
+	fn word(&mut self, word: &'static str, json: Json<'a>) -> Result<Json<'a>, JsonError> {
+		let start = self.offset();
+		if !self.text[start..].starts_with(word) {
+			return Err(self.error("expected a value"));
+		}
+
+		for _ in 0..word.len() {
+			self.next();
+		}
+		Ok(json)
+	}
+
+	fn number(&mut self) -> Result<&'a str, JsonError> {
+		let start = self.offset();
+
+		if self.peek() == Some('-') {
+			self.next();
+		}
+		match self.peek() {
+			Some('0') => {
+				self.next();
+			}
+			Some('1'..='9') => self.digits(),
+			_ => return Err(self.error("expected a digit")),
+		}
+
+		if self.peek() == Some('.') {
+			self.next();
+			if !matches!(self.peek(), Some('0'..='9')) {
+				return Err(self.error("expected a digit"));
+			}
+			self.digits();
+		}
+
+		if matches!(self.peek(), Some('e' | 'E')) {
+			self.next();
+			if matches!(self.peek(), Some('+' | '-')) {
+				self.next();
+			}
+			if !matches!(self.peek(), Some('0'..='9')) {
+				return Err(self.error("expected a digit"));
+			}
+			self.digits();
+		}
+
+		let end = self.offset();
+		Ok(&self.text[start..end])
+	}
+
+	fn digits(&mut self) {
+		while matches!(self.peek(), Some('0'..='9')) {
+			self.next();
+		}
+	}
+
+	fn string(&mut self) -> Result<String, JsonError> {
+		self.next();
+		let mut string = String::new();
+
+		loop {
+			match self.peek() {
+				None => return Err(self.error("expected the string to end")),
+				Some(c) if c.is_control() && c != '\u{7f}' => {
+					return Err(self.error("expected control characters to be escaped"))
+				}
+				_ => (),
+			}
+
+			match self.next() {
+				Some('"') => return Ok(string),
+				Some('\\') => {
+					let escaped = match self.next() {
+						Some('"') => '"',
+						Some('\\') => '\\',
+						Some('/') => '/',
+						Some('b') => '\u{8}',
+						Some('f') => '\u{c}',
+						Some('n') => '\n',
+						Some('r') => '\r',
+						Some('t') => '\t',
+						Some('u') => self.unicode_escape()?,
+						_ => return Err(self.error("expected a valid escape")),
+					};
+					string.push(escaped);
+				}
+				Some(c) => string.push(c),
+				None => unreachable!(),
+			}
+		}
+	}
+
+	/// The character of a `\u` escape, which might be two of them if it's
+	/// outside of the basic multilingual plane.
+	fn unicode_escape(&mut self) -> Result<char, JsonError> {
+		let high = self.hex()?;
+		if !(0xD800..0xDC00).contains(&high) {
+			return char::from_u32(high).ok_or_else(|| self.error("expected a valid character"));
+		}
+
+		if self.next() != Some('\\') || self.next() != Some('u') {
+			return Err(self.error("expected the second half of a surrogate pair"));
+		}
+		let low = self.hex()?;
+		if !(0xDC00..0xE000).contains(&low) {
+			return Err(self.error("expected the second half of a surrogate pair"));
+		}
+
+		let c = 0x10000 + ((high - 0xD800) << 10) + (low - 0xDC00);
+		char::from_u32(c).ok_or_else(|| self.error("expected a valid character"))
+	}
+
+	fn hex(&mut self) -> Result<u32, JsonError> {
+		let mut value = 0;
+		for _ in 0..4 {
+			let digit = self
+				.peek()
+				.and_then(|c| c.to_digit(16))
+				.ok_or_else(|| self.error("expected four hex digits"))?;
+
+			self.next();
+			value = value * 16 + digit;
+		}
+
+		Ok(value)
+	}
+}
+
+impl StdError for JsonError {}
+impl fmt::Display for JsonError {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		write!(
+			f,
+			"JSON line {} column {}: {}",
+			self.line, self.column, self.kind
+		)
+	}
+}
+
+impl fmt::Display for JsonErrorKind {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			JsonErrorKind::Syntax(message) => write!(f, "{message}"),
+			JsonErrorKind::NotAnObject => write!(f, "a document has to be an object"),
+			JsonErrorKind::NestedArray => write!(f, "arrays can't be in arrays"),
+			JsonErrorKind::InvalidOwnValue => {
+				write!(f, "{VALUE_MEMBER} can't be an array or an object")
+			}
+			JsonErrorKind::InvalidKey(key) => write!(f, "'{key}' can't be a key"),
+			JsonErrorKind::ReservedKey(key) => {
+				write!(
+					f,
+					"'{key}' is reserved, write '${key}' for a key starting with $"
+				)
+			}
+			JsonErrorKind::MultilineValue => write!(f, "values can't have line breaks"),
+		}
+	}
+}
+
+fn write_object<'a, I>(json: &mut String, own: Option<&str>, values: I, depth: usize)
+where
+	I: Iterator<Item = &'a Value>,
+{
+	// Group values by key, in the order keys first show up
+	let mut members: Vec<(&str, Vec<&Value>)> = vec![];
+	let mut positions: HashMap<&str, usize> = HashMap::new();
+	for value in values {
+		match positions.get(value.key.as_str()) {
+			Some(&idx) => members[idx].1.push(value),
+			None => {
+				positions.insert(&value.key, members.len());
+				members.push((&value.key, vec![value]));
+			}
+		}
+	}
+
+	if own.is_none() && members.is_empty() {
+		json.push_str("{}");
+		return;
+	}
+
+	json.push('{');
+	let mut first = true;
+	let mut member = |json: &mut String, key: &str| {
+		if !first {
+			json.push(',');
+		}
+		first = false;
+
+		newline(json, depth + 1);
+		write_string(json, key);
+		json.push_str(": ");
+	};
+
+	if let Some(own) = own {
+		member(json, VALUE_MEMBER);
+		write_string(json, own);
+	}
+
+	for (key, values) in members {
+		if key.starts_with('$') {
+			member(json, &format!("${key}"));
+		} else {
+			member(json, key);
+		}
+
+		match values.as_slice() {
+			[value] => write_value(json, value, depth + 1),
+			values => {
+				json.push('[');
+				for (idx, value) in values.iter().enumerate() {
+					if idx > 0 {
+						json.push(',');
+					}
+					newline(json, depth + 2);
+					write_value(json, value, depth + 2);
+				}
+				newline(json, depth + 1);
+				json.push(']');
+			}
+		}
+	}
+
+	newline(json, depth);
+	json.push('}');
+}
+
+fn write_value(json: &mut String, value: &Value, depth: usize) {
+	let mut children = value.values().peekable();
+	let raw = match value.value() {
+		Some(_) if value.is_secret() => Some(REDACTED),
+		raw => raw,
+	};
+
+	if children.peek().is_some() {
+		write_object(json, raw, children, depth);
+	} else {
+		match raw {
+			None => json.push_str("null"),
+			Some(raw) => write_string(json, raw),
+		}
+	}
+}
+
+fn newline(json: &mut String, depth: usize) {
+	json.push('\n');
+	for _ in 0..depth {
+		json.push('\t');
+	}
+}
+
+fn write_string(json: &mut String, s: &str) {
+	json.push('"');
+	for c in s.chars() {
+		match c {
+			'"' => json.push_str("\\\""),
+			'\\' => json.push_str("\\\\"),
+			'\n' => json.push_str("\\n"),
+			'\r' => json.push_str("\\r"),
+			'\t' => json.push_str("\\t"),
+			c if c.is_control() => {
+				// Writing to a String can't fail
+				let _ = write!(json, "\\u{:04x}", c as u32);
+			}
+			c => json.push(c),
+		}
+	}
+	json.push('"');
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	#[test]
+	fn mapping() {
+		let conf: Confindent = "# dropped\nEmpty\nQuote \"a\\b\"\tc\nParent\n\t$value x\n\tList 1\n\tList\nNested\n\tDeep\n\t\tDeeper yes"
+			.parse()
+			.unwrap();
+
+		assert_eq!(
+			conf.to_json(),
+			"{\n\t\"Empty\": null,\n\t\"Quote\": \"\\\"a\\\\b\\\"\\tc\",\n\t\"Parent\": {\n\t\t\"$$value\": \"x\",\n\t\t\"List\": [\n\t\t\t\"1\",\n\t\t\tnull\n\t\t]\n\t},\n\t\"Nested\": {\n\t\t\"Deep\": {\n\t\t\t\"Deeper\": \"yes\"\n\t\t}\n\t}\n}"
+		);
+		assert_eq!(Confindent::new().to_json(), "{}");
+	}
+
+	#[test]
+	fn roundtrip() {
+		let text =
+			"Host a\n\tPort 22\n\tAlias x\n\tAlias y\n\t$key z\nHost b\nEmpty\nParent\n\tChild\n";
+		let conf: Confindent = text.parse().unwrap();
+
+		let back = Confindent::from_json(&conf.to_json()).unwrap();
+		assert_eq!(back.to_string(), text);
+	}
+
+	#[test]
+	fn json_values() {
+		let json = r#"{
+			"Number": -1.5e3, "Yes": true, "None": null, "Escaped": "a\"\u00e9\ud83d\ude00",
+			"Gone": [], "Also": {}, "Own": {"$value": 7, "Child": "c"}
+		}"#;
+		let conf = Confindent::from_json(json).unwrap();
+
+		assert_eq!(
+			conf.to_string(),
+			"Number -1.5e3\nYes true\nNone\nEscaped a\"\u{e9}\u{1f600}\nOwn 7\n\tChild c\n"
+		);
+	}
+
+	#[test]
+	fn unrepresentable_json() {
+		let kind = |json| Confindent::from_json(json).unwrap_err().kind;
+
+		assert_eq!(kind("[1]"), JsonErrorKind::NotAnObject);
+		assert_eq!(kind(r#"{"A": [[1]]}"#), JsonErrorKind::NestedArray);
+		assert_eq!(
+			kind(r#"{"A": {"$value": [1]}}"#),
+			JsonErrorKind::InvalidOwnValue
+		);
+		assert_eq!(
+			kind(r#"{"A B": 1}"#),
+			JsonErrorKind::InvalidKey("A B".into())
+		);
+		assert_eq!(
+			kind(r##"{"#A": 1}"##),
+			JsonErrorKind::InvalidKey("#A".into())
+		);
+		assert_eq!(
+			kind(r#"{"$A": 1}"#),
+			JsonErrorKind::ReservedKey("$A".into())
+		);
+		assert_eq!(kind(r#"{"A": "x\ny"}"#), JsonErrorKind::MultilineValue);
+	}
+
+	#[test]
+	fn syntax_errors_have_positions() {
+		let err = |json| Confindent::from_json(json).unwrap_err();
+
+		assert_eq!(
+			err("{\n\t\"A\": tru\n}"),
+			JsonError {
+				line: 1,
+				column: 6,
+				kind: JsonErrorKind::Syntax("expected a value")
+			}
+		);
+		assert_eq!(
+			err(r#"{"A": 1,}"#).kind,
+			JsonErrorKind::Syntax("expected a key")
+		);
+		assert_eq!(
+			err(r#"{"A": 01}"#).kind,
+			JsonErrorKind::Syntax("expected a comma or a closing brace")
+		);
+		assert_eq!(
+			err(r#"{"A": "\x"}"#).kind,
+			JsonErrorKind::Syntax("expected a valid escape")
+		);
+		assert_eq!(
+			err("{} {}").kind,
+			JsonErrorKind::Syntax("expected the end of the document")
+		);
+		assert_eq!(
+			err(&"[".repeat(1000)).kind,
+			JsonErrorKind::Syntax("nothing nested so deeply")
+		);
+	}
+}
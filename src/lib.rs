@@ -38,31 +38,86 @@
 //!	println!("User {username}: {id} Contact: {email}");
 //! ```
 
+mod borrowed;
 mod error;
+mod import;
 mod indent;
+mod index;
+mod inherit;
+mod json;
 mod line;
+mod matching;
+mod options;
+mod parser;
+mod resolve;
+mod save;
+pub mod schema;
+mod secret;
+mod ssh;
+mod typed;
 mod value;
+mod watch;
 
 use core::fmt;
 use std::{
-	fs::{self, File},
-	io::{self, Write},
+	fs,
+	io::{self, BufRead},
 	path::Path,
 	str::FromStr,
+	time::Duration,
 };
 
-pub use error::{ParseError, ParseErrorKind, ValueParseError};
+pub use borrowed::{ConfindentRef, ValueRef};
+pub use error::{ParseError, ParseErrorKind, ValueError, ValueErrorKind};
+pub use import::{ImportError, ImportErrorKind};
 use indent::Indent;
+use index::KeyIndex;
+pub use index::{Children, KeyCase};
+pub use inherit::{InheritError, InheritErrorKind};
+pub use json::{JsonError, JsonErrorKind};
 use line::Line;
+pub use line::LineEnding;
+pub use matching::{MatchContext, MatchError, MatchErrorKind};
+pub use options::{AllowedIndent, ParseOptions, Separator};
+use parser::TreeBuilder;
+pub use parser::{Event, Parser};
+pub use resolve::{ResolveError, Resolver, Resolvers};
+pub use save::SaveOptions;
+pub use secret::Secret;
+pub use typed::Delimiter;
 pub use value::Value;
+pub use watch::{Reload, Watcher, Watching};
 
 /// A parsed configuration file. This struct holds the values with no indentation.
-#[derive(Debug, PartialEq)]
+///
+/// Printing a `Confindent` gives back exactly what was parsed, byte order
+/// mark, line endings, and all, so long as you didn't change anything.
+#[derive(Clone, Debug, PartialEq)]
 pub struct Confindent {
 	children: Vec<Line>,
+	index: KeyIndex,
+	/// Whether the document started with a byte order mark
+	bom: bool,
+	/// The line ending of the first line, used for lines without their own
+	line_ending: LineEnding,
+	/// Whether the last line had a line ending
+	final_newline: bool,
+	warnings: Vec<ParseError>,
 }
 
 impl Confindent {
+	/// Create an empty document.
+	pub fn new() -> Self {
+		Self {
+			children: vec![],
+			index: KeyIndex::default(),
+			bom: false,
+			line_ending: LineEnding::Lf,
+			final_newline: true,
+			warnings: vec![],
+		}
+	}
+
 	/// Tries to read and parse the file at the provided path.
 	///
 	/// # Returns
@@ -70,45 +125,105 @@ impl Confindent {
 	/// A new [Confindent] if the file was read and parsed successfully, or a
 	/// [ParseError] if not.
 	pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self, ParseError> {
+		Self::from_file_with(path, &ParseOptions::default())
+	}
+
+	/// Read and parse the file at the provided path the way the options say
+	/// to. See [ParseOptions].
+	pub fn from_file_with<P: AsRef<Path>>(
+		path: P,
+		options: &ParseOptions,
+	) -> Result<Self, ParseError> {
 		let string = fs::read_to_string(path).map_err(|_| ParseError {
 			line: 0,
 			kind: ParseErrorKind::FileReadError,
 		})?;
 
-		Confindent::from_str(&string)
+		Confindent::from_str_with(&string, options)
+	}
+
+	/// Parse a document the way the options say to. See [ParseOptions].
+	pub fn from_str_with(s: &str, options: &ParseOptions) -> Result<Self, ParseError> {
+		Self::from_reader_with(s.as_bytes(), options)
+	}
+
+	/// Read and parse a document from anything that implements [BufRead].
+	///
+	/// This builds the tree from a [Parser]. If you don't need the tree,
+	/// you can use the parser directly.
+	pub fn from_reader<R: BufRead>(reader: R) -> Result<Self, ParseError> {
+		Self::from_reader_with(reader, &ParseOptions::default())
 	}
 
+	/// Like [from_reader](Confindent::from_reader), but reading the document
+	/// the way the options say to. See [ParseOptions].
+	pub fn from_reader_with<R: BufRead>(
+		reader: R,
+		options: &ParseOptions,
+	) -> Result<Self, ParseError> {
+		let mut parser = Parser::with_options(reader, options);
+		let mut tree = TreeBuilder::<Value>::new();
+
+		while let Some(placed) = parser.next_line() {
+			tree.push(placed?);
+		}
+
+		Ok(Self {
+			children: tree.finish(),
+			index: KeyIndex::default(),
+			bom: parser.nesting.bom,
+			line_ending: parser.nesting.line_ending.unwrap_or_default(),
+			final_newline: parser.nesting.final_newline,
+			warnings: parser.nesting.warnings,
+		})
+	}
+
+	/// The lines that would have been errors if they weren't tolerated by
+	/// [mixed_indent](ParseOptions::mixed_indent), one for each line, as the
+	/// errors they would have been. Empty for anything else.
+	pub fn warnings(&self) -> &[ParseError] {
+		&self.warnings
+	}
+
+	/// Save the document to `path`, replacing the file that's there.
+	///
+	/// A reader sees the old document or the new one, never part of one,
+	/// and the file keeps its permissions. See [save_with](Confindent::save_with)
+	/// for backups and locking.
 	pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), io::Error> {
-		let mut file = File::create(path)?;
-		write!(file, "{self}")
+		self.save_with(path, &SaveOptions::default())
 	}
 
 	pub fn get<S: AsRef<str>>(&self, path: S) -> Option<&str> {
 		self.get_delim(path, '/')
 	}
 
-	pub fn get_parse<S: AsRef<str>, T: FromStr>(&self, path: S) -> Result<T, ValueParseError<T>> {
+	pub fn get_parse<S: AsRef<str>, T>(&self, path: S) -> Result<T, ValueError>
+	where
+		T: FromStr,
+		T::Err: fmt::Display,
+	{
 		self.get_delim_parse(path, '/')
 	}
 
-	pub fn get_delim_parse<S: AsRef<str>, T: FromStr>(
+	pub fn get_delim_parse<S: AsRef<str>, T>(
 		&self,
 		path: S,
 		delimeter: char,
-	) -> Result<T, ValueParseError<T>> {
-		self.get_delim(path, delimeter)
-			.map(|child| child.parse().map_err(|e| ValueParseError::ParseError(e)))
-			.unwrap_or(Err(ValueParseError::NoValue))
+	) -> Result<T, ValueError>
+	where
+		T: FromStr,
+		T::Err: fmt::Display,
+	{
+		let path = path.as_ref();
+		typed::typed(self.walk(path, delimeter), path, typed::from_str)
 	}
 
 	pub fn get_delim<S: AsRef<str>>(&self, path: S, delimeter: char) -> Option<&str> {
 		let path = path.as_ref();
 		let mut splits = path.split(delimeter);
 
-		let mut current = match splits.next().and_then(|key| self.child(key)) {
-			None => return None,
-			Some(child) => child,
-		};
+		let mut current = splits.next().and_then(|key| self.child(key))?;
 
 		for key in splits {
 			match current.child(key) {
@@ -124,27 +239,32 @@ impl Confindent {
 	///
 	/// See [Value::child] for more.
 	pub fn child<S: AsRef<str>>(&self, key: S) -> Option<&Value> {
-		self.values().find(|value| value.key == key.as_ref())
+		self.children_iter(key).next()
 	}
 
 	pub fn child_mut<S: AsRef<str>>(&mut self, key: S) -> Option<&mut Value> {
-		self.values_mut().find(|value| value.key == key.as_ref())
+		value::child_mut(&mut self.children, &self.index, key.as_ref())
 	}
 
 	/// Get all of the direct children with the provided key.
 	///
 	/// See [Value::children] for more.
 	pub fn children<S: AsRef<str>>(&self, key: S) -> Vec<&Value> {
-		self.values()
-			.filter(|value| value.key == key.as_ref())
-			.collect()
+		self.children_iter(key).collect()
+	}
+
+	/// Iterate over the direct children with the provided key.
+	///
+	/// See [Value::children_iter] for more.
+	pub fn children_iter<S: AsRef<str>>(&self, key: S) -> Children<'_, S> {
+		Children::new(&self.children, &self.index, key)
 	}
 
 	/// Check if there are any direct children with the provided key.
 	///
 	/// See [Value::has_child] for more.
 	pub fn has_child<S: AsRef<str>>(&self, key: S) -> bool {
-		self.values().any(|value| value.key == key.as_ref())
+		self.children_iter(key).next().is_some()
 	}
 
 	/// Get the value of a child with the provided key.
@@ -164,169 +284,266 @@ impl Confindent {
 	/// Pase the value of a child into your desired type.
 	///
 	/// Please, see [Value::child_parse] for more.
-	pub fn child_parse<S: AsRef<str>, T: FromStr>(&self, key: S) -> Result<T, ValueParseError<T>> {
-		self.child(key)
-			.map(|child| child.parse())
-			.unwrap_or(Err(ValueParseError::NoValue))
+	pub fn child_parse<S: AsRef<str>, T>(&self, key: S) -> Result<T, ValueError>
+	where
+		T: FromStr,
+		T::Err: fmt::Display,
+	{
+		let key = key.as_ref();
+		typed::typed(self.child(key), key, typed::from_str)
 	}
 
-	pub fn values(&self) -> ValueIterator {
-		ValueIterator {
-			inner: self.children.iter(),
-		}
+	/// Whether a flag at a `/` separated path of keys is set.
+	///
+	/// See [Value::flag] for more.
+	pub fn flag<S: AsRef<str>>(&self, path: S) -> Result<bool, ValueError> {
+		let path = path.as_ref();
+		typed::flag(self.walk(path, '/'), path)
 	}
 
-	pub fn values_mut(&mut self) -> ValueIteratorMut {
-		ValueIteratorMut {
-			inner: self.children.iter_mut(),
-		}
+	/// Get a value from a `/` separated path of keys as a bool.
+	///
+	/// See [Value::parse_bool] for more.
+	pub fn get_bool<S: AsRef<str>>(&self, path: S) -> Result<bool, ValueError> {
+		let path = path.as_ref();
+		typed::typed(self.walk(path, '/'), path, typed::parse_bool)
 	}
 
-	fn push(&mut self, mut line: Line) -> Result<(), ParseErrorKind> {
-		let indent = match &mut line {
-			Line::Blank(_) => {
-				self.push_last(line);
-				return Ok(());
-			}
-			Line::Value(v) => &mut v.indent,
-			Line::Comment { ref mut indent, .. } => indent,
-		};
+	/// Get a value from a `/` separated path of keys as a duration.
+	///
+	/// See [Value::parse_duration] for more.
+	pub fn get_duration<S: AsRef<str>>(&self, path: S) -> Result<Duration, ValueError> {
+		let path = path.as_ref();
+		typed::typed(self.walk(path, '/'), path, typed::parse_duration)
+	}
 
-		// Handle the easy stuff first
-		if Indent::Empty == *indent {
-			self.children.push(line);
-			return Ok(());
-		} else if self.children.is_empty() {
-			return Err(ParseErrorKind::StartedIndented);
+	/// Get a value from a `/` separated path of keys as a number of bytes.
+	///
+	/// See [Value::parse_bytes] for more.
+	pub fn get_bytes<S: AsRef<str>>(&self, path: S) -> Result<u64, ValueError> {
+		let path = path.as_ref();
+		typed::typed(self.walk(path, '/'), path, typed::parse_bytes)
+	}
+
+	fn walk(&self, path: &str, delimiter: char) -> Option<&Value> {
+		typed::walk(&self.children, &self.index, path, delimiter)
+	}
+
+	/// Add a value to the end of the document.
+	///
+	/// See [Value::add_child] for more.
+	pub fn add_child(&mut self, value: Value) {
+		self.push_line(Line::Value(value));
+	}
+
+	/// Add a comment to the end of the document. The comment is written
+	/// after the `#` as it is, so start it with a space if you'd like one
+	/// there.
+	///
+	/// # Example
+	///
+	/// ```rust
+	/// use confindent::{Confindent, Value};
+	///
+	/// let mut conf = Confindent::new();
+	/// conf.add_comment(" written by hand");
+	/// conf.add_child(Value::new("Host", Some("example.com")));
+	///
+	/// assert_eq!(conf.to_string(), "# written by hand\nHost example.com\n");
+	/// ```
+	pub fn add_comment<S: Into<String>>(&mut self, comment: S) {
+		self.push_line(Line::from((Indent::Empty, comment.into())));
+	}
+
+	/// Add a line to the end of the document without any indent, with
+	/// values separated from their keys like the ones already there.
+	pub(crate) fn push_line(&mut self, mut line: Line) {
+		line.set_ending(self.children.last().and_then(Line::last_ending));
+		line.reindent(Indent::Empty);
+		if let Line::Value(value) = &mut line {
+			value.set_key_case(self.index.case());
+			if let Some(separator) = value::separator_of(&self.children) {
+				value.separate_with(separator);
+			}
+			self.index.pushed(&value.key, self.children.len());
 		}
+		self.children.push(line);
+	}
 
-		let mut curr = self.values_mut().last().unwrap();
-		match indent {
-			Indent::Tabs { count: tabsize, .. } => loop {
-				match curr.values_mut().last() {
-					None => {
-						indent.delta_from(&curr.indent)?;
-						curr.children.push(line);
-						break;
-					}
-					Some(child) => match child.indent {
-						Indent::Empty => unreachable!(),
-						Indent::Spaces { .. } => return Err(ParseErrorKind::TabsWithSpaces),
-						Indent::Tabs {
-							count: child_tabsize,
-							..
-						} => {
-							if *tabsize == child_tabsize {
-								indent.delta_from(&child.indent)?;
-								curr.children.push(line);
-								break;
-							} else {
-								curr = curr.values_mut().last().unwrap();
-							}
-						}
-					},
-				}
-			},
-			Indent::Spaces { count: spaces, .. } => loop {
-				match curr.values_mut().last() {
-					None => {
-						curr.children.push(line);
-						break;
-					}
-					Some(child) => match child.indent {
-						Indent::Empty => unreachable!(),
-						Indent::Tabs { .. } => return Err(ParseErrorKind::SpacesWithTabs),
-						Indent::Spaces {
-							count: child_spaces,
-							..
-						} => {
-							if *spaces == child_spaces {
-								indent.delta_from(&child.indent)?;
-								curr.children.push(line);
-								break;
-							} else {
-								curr = curr.values_mut().last().unwrap();
-							}
-						}
-					},
-				}
-			},
-			_ => unreachable!(),
+	/// Set the value at a `/` separated path of keys, adding any keys that
+	/// aren't there yet. Returns the value that was set.
+	///
+	/// See [Value::set] for more.
+	pub fn set<S: AsRef<str>, V: fmt::Display>(&mut self, path: S, value: Option<V>) -> &mut Value {
+		let path = path.as_ref();
+		let (first, rest) = path.split_once('/').unwrap_or((path, ""));
+
+		if !self.has_child(first) {
+			self.add_child(Value::new(first, None::<&str>));
 		}
+		let separator = value::separator_of(&self.children).map(<_>::to_owned);
+		let current = value::child_mut(&mut self.children, &self.index, first).unwrap();
 
-		Ok(())
+		match rest {
+			"" => {
+				current.set_value_like(value, separator);
+				current
+			}
+			rest => current.set_styled(rest, value, separator),
+		}
 	}
 
-	/// Push the provided [Line] to the last, deepest node
-	fn push_last(&mut self, line: Line) {
-		if self.values().count() == 0 {
-			self.children.push(line);
-			return;
+	/// Remove the first value at a `/` separated path of keys, and return
+	/// it if there was one.
+	///
+	/// See [Value::remove] for more.
+	pub fn remove<S: AsRef<str>>(&mut self, path: S) -> Option<Value> {
+		let path = path.as_ref();
+
+		match path.split_once('/') {
+			None => value::remove_child(&mut self.children, &mut self.index, path),
+			Some((first, rest)) => self.child_mut(first)?.remove(rest),
 		}
+	}
 
-		let mut curr = self.values_mut().last().unwrap();
-		loop {
-			match curr.values_mut().last() {
-				None => {
-					curr.children.push(line);
-					return;
-				}
-				// If we use the value from Some here, we got a double-mutable reference error...
-				Some(_) => curr = curr.values_mut().last().unwrap(),
+	/// Indent everything with tabs, one for each level, and take any
+	/// whitespace off of blank lines.
+	///
+	/// # Example
+	///
+	/// ```rust
+	/// use confindent::Confindent;
+	///
+	/// let mut conf: Confindent = "Host\n    Port 22\n    Proxy\n      Jump a\n".parse().unwrap();
+	/// conf.normalize();
+	///
+	/// assert_eq!(conf.to_string(), "Host\n\tPort 22\n\tProxy\n\t\tJump a\n");
+	/// ```
+	pub fn normalize(&mut self) {
+		for line in &mut self.children {
+			match line {
+				Line::Value(value) => value.normalize(Indent::Empty),
+				Line::Blank { whitespace, .. } => whitespace.clear(),
+				Line::Comment { .. } => (),
 			}
 		}
 	}
-}
 
-impl FromStr for Confindent {
-	type Err = ParseError;
+	/// How keys are compared when looking them up.
+	pub fn key_case(&self) -> KeyCase {
+		self.index.case()
+	}
 
-	fn from_str(s: &str) -> Result<Self, Self::Err> {
-		let mut ret = Self { children: vec![] };
-		let lines = s.lines().enumerate();
-		let add_ln =
-			|e: ParseErrorKind, ln: usize| -> ParseError { ParseError { line: ln, kind: e } };
-
-		for (line_number, line) in lines {
-			if blank_line(line) {
-				ret.push_last(Line::Blank(line.to_owned()));
-				continue;
-			}
+	/// Compare keys the provided way when looking anything up, by a key or
+	/// by a path, anywhere in the document. Values added later compare keys
+	/// the same way. See [KeyCase] for an example.
+	pub fn set_key_case(&mut self, case: KeyCase) {
+		self.index.set_case(case);
+		for value in self.values_mut() {
+			value.set_key_case(case);
+		}
+	}
 
-			let (indent, other) =
-				Value::split_whitespace(line).map_err(|e| add_ln(e, line_number))?;
+	/// The line ending used for lines that don't have their own. This is
+	/// the ending of the first line of a parsed document.
+	pub fn line_ending(&self) -> LineEnding {
+		self.line_ending
+	}
 
-			let line = if let Some(comment) = other.strip_prefix('#') {
-				Line::Comment {
-					indent,
-					comment: comment.into(),
-				}
-			} else {
-				Line::Value(Value::from_str(line).map_err(|e| add_ln(e, line_number))?)
-			};
+	/// Change the line ending of every line in the document.
+	///
+	/// # Example
+	///
+	/// ```rust
+	/// use confindent::{Confindent, LineEnding};
+	///
+	/// let mut conf: Confindent = "Key Value\r\n\tChild Value\n".parse().unwrap();
+	/// conf.set_line_ending(LineEnding::Lf);
+	///
+	/// assert_eq!(conf.to_string(), "Key Value\n\tChild Value\n");
+	/// ```
+	pub fn set_line_ending(&mut self, ending: LineEnding) {
+		self.line_ending = ending;
+		for child in self.children.iter_mut() {
+			child.set_ending(None);
+		}
+	}
 
-			ret.push(line).map_err(|e| add_ln(e, line_number))?;
+	pub fn values(&self) -> ValueIterator<'_> {
+		ValueIterator {
+			inner: self.children.iter(),
 		}
+	}
 
-		Ok(ret)
+	pub fn values_mut(&mut self) -> ValueIteratorMut<'_> {
+		ValueIteratorMut {
+			inner: self.children.iter_mut(),
+		}
 	}
 }
 
-fn blank_line(s: &str) -> bool {
-	for ch in s.chars() {
-		if !ch.is_whitespace() {
-			return false;
-		}
+impl FromStr for Confindent {
+	type Err = ParseError;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		Self::from_reader(s.as_bytes())
+	}
+}
+
+impl Default for Confindent {
+	fn default() -> Self {
+		Self::new()
 	}
-	true
 }
 
 impl fmt::Display for Confindent {
 	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		self.write_to(f, true)
+	}
+}
+
+impl Confindent {
+	/// Write the document, leaving out secret values if `redact`.
+	fn write_to<W: fmt::Write>(&self, w: &mut W, redact: bool) -> fmt::Result {
+		if self.bom {
+			w.write_char('\u{feff}')?;
+		}
+
+		if self.final_newline {
+			for child in &self.children {
+				child.write_to(w, self.line_ending, redact)?;
+			}
+			return Ok(());
+		}
+
+		// The last line didn't have an ending, so we have to find it and
+		// leave it off. It's easiest to just write everything and trim.
+		let mut buffer = String::new();
 		for child in &self.children {
-			child.fmt(f)?;
+			child.write_to(&mut buffer, self.line_ending, redact)?;
 		}
-		Ok(())
+
+		let trimmed = buffer
+			.strip_suffix(self.line_ending.as_str())
+			.or_else(|| buffer.strip_suffix(LineEnding::CrLf.as_str()))
+			.or_else(|| buffer.strip_suffix(LineEnding::Lf.as_str()))
+			.unwrap_or(&buffer);
+
+		w.write_str(trimmed)
+	}
+
+	/// The document as it's saved, with the values of
+	/// [secrets](Confindent::mark_secret) and all.
+	pub fn unredacted(&self) -> impl fmt::Display + '_ {
+		struct Unredacted<'a>(&'a Confindent);
+
+		impl fmt::Display for Unredacted<'_> {
+			fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+				self.0.write_to(f, false)
+			}
+		}
+
+		Unredacted(self)
 	}
 }
 
@@ -383,7 +600,9 @@ mod test {
 		assert_eq!(
 			Confindent::from_str(single).unwrap(),
 			Confindent {
-				children: vec![value!(Indent::Empty, "Key", "Value")]
+				children: vec![value!(Indent::Empty, "Key", "Value")],
+				final_newline: false,
+				..Default::default()
 			}
 		);
 	}
@@ -398,7 +617,9 @@ mod test {
 				children: vec![
 					value!(Indent::Empty, "Key1", "Value1"),
 					value!(Indent::Empty, "Key2", "Value2")
-				]
+				],
+				final_newline: false,
+				..Default::default()
 			}
 		);
 	}
@@ -414,12 +635,21 @@ mod test {
 					indent: Indent::Empty,
 					key: "Key1".into(),
 					value: Some("Value1".into()),
+					separator: None,
+					ending: None,
+					line: None,
+					index: KeyIndex::default(),
+					secret: false,
+					inherited: false,
+					written_indent: None,
 					children: vec![value!(
 						Indent::Tabs { count: 1, delta: 1 },
 						"Key2",
 						"Value2"
 					)]
-				})]
+				})],
+				final_newline: false,
+				..Default::default()
 			}
 		);
 	}
@@ -435,17 +665,33 @@ mod test {
 					indent: Indent::Empty,
 					key: "Key1".into(),
 					value: Some("Value1".into()),
+					separator: None,
+					ending: None,
+					line: None,
+					index: KeyIndex::default(),
+					secret: false,
+					inherited: false,
+					written_indent: None,
 					children: vec![Line::Value(Value {
 						indent: Indent::Tabs { count: 1, delta: 1 },
 						key: "Key2".into(),
 						value: Some("Value2".into()),
+						separator: None,
+						ending: None,
+						line: None,
+						index: KeyIndex::default(),
+						secret: false,
+						inherited: false,
+						written_indent: None,
 						children: vec![value!(
 							Indent::Tabs { count: 2, delta: 1 },
 							"Key3",
 							"Value3"
 						)]
 					})]
-				})]
+				})],
+				final_newline: false,
+				..Default::default()
 			}
 		);
 	}
@@ -462,6 +708,13 @@ mod test {
 						indent: Indent::Empty,
 						key: "Key1".into(),
 						value: Some("Value1".into()),
+						separator: None,
+						ending: None,
+						line: None,
+						index: KeyIndex::default(),
+						secret: false,
+						inherited: false,
+						written_indent: None,
 						children: vec![value!(
 							Indent::Tabs { count: 1, delta: 1 },
 							"Key2",
@@ -469,7 +722,9 @@ mod test {
 						)]
 					}),
 					value!(Indent::Empty, "Key3", "Value3")
-				]
+				],
+				final_newline: false,
+				..Default::default()
 			}
 		);
 	}
@@ -493,6 +748,123 @@ MoreRoot value
 
 		assert_eq!(raw, string)
 	}
+
+	#[test]
+	fn roundtrip_lossless() {
+		let crlf = "Root value\r\n\tKey v\r\n\t\r\n\t# Comment\r\nMoreRoot\r\n";
+		let bom = "\u{feff}Root value\n\tKey v\n";
+		let trailing = "Root \n\tKey v  \n\t\t\n";
+		let no_final = "Root value\n\tKey v";
+		let mixed = "Root value\r\n\tKey v\n\tKey w\r\n";
+
+		for raw in [crlf, bom, trailing, no_final, mixed] {
+			let conf: Confindent = raw.parse().unwrap();
+			assert_eq!(raw, conf.to_string())
+		}
+	}
+
+	#[test]
+	fn comment_keeps_its_place() {
+		let raw = "A\n\tB\n\t\tC\n\t# comment\n\tD\n";
+		let conf: Confindent = raw.parse().unwrap();
+
+		assert_eq!(raw, conf.to_string());
+		assert!(conf.child("A").unwrap().has_child("D"));
+	}
+
+	#[test]
+	fn bom_not_in_key() {
+		let conf: Confindent = "\u{feff}Key Value".parse().unwrap();
+		assert_eq!(conf.child_value("Key"), Some("Value"));
+	}
+
+	#[test]
+	fn added_values_keep_line_ending() {
+		let mut conf: Confindent = "Root value\r\n\tKey v".parse().unwrap();
+
+		let mut added = Value::new("Added", Some("value"));
+		added.add_child(Value::new("Child", None::<&str>));
+		conf.child_mut("Root").unwrap().add_child(added);
+		conf.add_child(Value::new("Last", Some("one")));
+
+		assert_eq!(
+			conf.to_string(),
+			"Root value\r\n\tKey v\r\n\tAdded value\r\n\t\tChild\r\nLast one"
+		);
+	}
+
+	#[test]
+	fn set_and_remove_paths() {
+		let mut text = String::from("Root\n");
+		for i in 0..20 {
+			text.push_str(&format!("\tKey{i} {i}\n"));
+		}
+		let mut conf: Confindent = text.parse().unwrap();
+
+		// Build the index before changing things
+		assert_eq!(conf.get("Root/Key10"), Some("10"));
+		assert_eq!(conf.remove("Root/Key3").unwrap().value(), Some("3"));
+		assert_eq!(conf.get("Root/Key10"), Some("10"));
+		assert_eq!(conf.get("Root/Key3"), None);
+		assert!(conf.remove("Root/Key3").is_none());
+		assert!(conf.remove("Missing/Key").is_none());
+
+		conf.set("Root/Key4", Some("four"));
+		conf.set("New/Deep", Some(1));
+		assert_eq!(conf.get("Root/Key4"), Some("four"));
+		assert_eq!(conf.get("New/Deep"), Some("1"));
+		assert!(conf.to_string().ends_with("\tKey19 19\nNew\n\tDeep 1\n"));
+
+		assert!(conf.remove("Root").is_some());
+		assert_eq!(conf.to_string(), "New\n\tDeep 1\n");
+	}
+
+	#[test]
+	fn edits_change_only_their_lines() {
+		let options = ParseOptions::new().separator(Separator::Char('='));
+		let text = "# server\r\nServer\r\n    Host  =  example.com   \n    Port=22\n    Flag\n\n    User = gen\n";
+		let mut conf = Confindent::from_str_with(text, &options).unwrap();
+
+		let changed = |conf: &Confindent| {
+			let printed = conf.to_string();
+			let lines: Vec<&str> = printed.split_inclusive('\n').collect();
+			let original: Vec<&str> = text.split_inclusive('\n').collect();
+			let same = original
+				.iter()
+				.zip(&lines)
+				.take_while(|(a, b)| a == b)
+				.count();
+			lines[same..]
+				.iter()
+				.map(|line| line.to_string())
+				.collect::<Vec<_>>()
+		};
+
+		conf.set("Server/Port", Some(2222));
+		assert_eq!(
+			changed(&conf),
+			["    Port=2222\n", "    Flag\n", "\n", "    User = gen\n"]
+		);
+		assert_eq!(conf.to_string().lines().nth(3), Some("    Port=2222"));
+
+		// A value that didn't have one, and new ones, look like their siblings
+		conf.set("Server/Port", Some(22));
+		conf.set("Server/Flag", Some("on"));
+		conf.set("Server/Proxy/Jump", Some("bastion"));
+		assert_eq!(
+			changed(&conf),
+			[
+				"    Flag  =  on\n",
+				"\n",
+				"    User = gen\n",
+				"    Proxy\n",
+				"        Jump  =  bastion\n"
+			]
+		);
+
+		conf.set("Server/Flag", None::<&str>);
+		assert_eq!(conf.to_string().lines().nth(4), Some("    Flag"));
+	}
 }
 
 // Code from the bottom of this page:
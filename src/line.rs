@@ -5,8 +5,93 @@ use crate::{indent::Indent, Value};
 #[derive(Debug, Clone, PartialEq)]
 pub enum Line {
 	Value(Value),
-	Comment { indent: Indent, comment: String },
-	Blank(String),
+	Comment {
+		indent: Indent,
+		/// The indent as it was written, if it mixed tabs and spaces so
+		/// `indent` can't write it. Moving the comment forgets it.
+		written_indent: Option<String>,
+		/// What started the comment if it wasn't a `#`
+		prefix: Option<String>,
+		comment: String,
+		/// The ending of this line if it differs from the document's
+		ending: Option<LineEnding>,
+	},
+	Blank {
+		whitespace: String,
+		/// The ending of this line if it differs from the document's
+		ending: Option<LineEnding>,
+	},
+}
+
+impl Line {
+	/// Write this line, and any children it might have, using `ending` for
+	/// lines that don't have their own, and leaving out secret values if
+	/// `redact`.
+	pub(crate) fn write_to<W: fmt::Write>(
+		&self,
+		w: &mut W,
+		ending: LineEnding,
+		redact: bool,
+	) -> fmt::Result {
+		match self {
+			Line::Blank {
+				whitespace,
+				ending: own,
+			} => write!(w, "{whitespace}{}", own.unwrap_or(ending)),
+			Line::Comment {
+				indent,
+				written_indent,
+				prefix,
+				comment,
+				ending: own,
+			} => {
+				match written_indent {
+					Some(written) => w.write_str(written)?,
+					None => write!(w, "{indent}")?,
+				}
+				let prefix = prefix.as_deref().unwrap_or("#");
+				write!(w, "{prefix}{comment}{}", own.unwrap_or(ending))
+			}
+			Line::Value(v) => v.write_to(w, ending, redact),
+		}
+	}
+
+	/// Give this line, and any children it has, their own ending. None
+	/// means the document's.
+	pub(crate) fn set_ending(&mut self, own: Option<LineEnding>) {
+		match self {
+			Line::Blank { ending, .. } | Line::Comment { ending, .. } => *ending = own,
+			Line::Value(v) => v.set_endings(own),
+		}
+	}
+
+	/// Move this line, and any children it has, to the indent.
+	pub(crate) fn reindent(&mut self, to: Indent) {
+		match self {
+			Line::Value(value) => value.reindent(to),
+			Line::Comment {
+				indent,
+				written_indent,
+				..
+			} => {
+				*indent = to;
+				*written_indent = None;
+			}
+			Line::Blank { .. } => (),
+		}
+	}
+
+	/// The ending of the last line this writes, which is its last child's if
+	/// it has children. None means the document's.
+	pub(crate) fn last_ending(&self) -> Option<LineEnding> {
+		match self {
+			Line::Blank { ending, .. } | Line::Comment { ending, .. } => *ending,
+			Line::Value(v) => match v.children.last() {
+				None => v.ending,
+				Some(child) => child.last_ending(),
+			},
+		}
+	}
 }
 
 impl From<Value> for Line {
@@ -19,7 +104,10 @@ impl From<(Indent, String)> for Line {
 	fn from(comment: (Indent, String)) -> Self {
 		Line::Comment {
 			indent: comment.0,
+			written_indent: None,
+			prefix: None,
 			comment: comment.1,
+			ending: None,
 		}
 	}
 }
@@ -28,17 +116,57 @@ impl From<(Indent, &str)> for Line {
 	fn from(comment: (Indent, &str)) -> Self {
 		Line::Comment {
 			indent: comment.0,
+			written_indent: None,
+			prefix: None,
 			comment: comment.1.into(),
+			ending: None,
 		}
 	}
 }
 
 impl fmt::Display for Line {
 	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		self.write_to(f, LineEnding::Lf, true)
+	}
+}
+
+/// The characters that end a line.
+///
+/// A document remembers the ending it was parsed with and uses it for any
+/// lines you add, so a file with Windows line endings stays that way.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum LineEnding {
+	/// A lone line feed, `\n`
+	#[default]
+	Lf,
+	/// A carriage return and line feed, `\r\n`
+	CrLf,
+}
+
+impl LineEnding {
+	/// The line ending as a string.
+	pub fn as_str(&self) -> &'static str {
 		match self {
-			Line::Blank(blnk) => writeln!(f, "{blnk}"),
-			Line::Comment { indent, comment } => writeln!(f, "{indent}#{comment}"),
-			Line::Value(v) => v.fmt(f),
+			LineEnding::Lf => "\n",
+			LineEnding::CrLf => "\r\n",
 		}
 	}
+
+	/// Split a line from the rest of the string, returning the line without
+	/// its ending, the ending if there was one, and the remainder.
+	pub(crate) fn split_line(s: &str) -> (&str, Option<LineEnding>, &str) {
+		match s.split_once('\n') {
+			None => (s, None, ""),
+			Some((line, rest)) => match line.strip_suffix('\r') {
+				None => (line, Some(LineEnding::Lf), rest),
+				Some(line) => (line, Some(LineEnding::CrLf), rest),
+			},
+		}
+	}
+}
+
+impl fmt::Display for LineEnding {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.write_str(self.as_str())
+	}
 }
@@ -0,0 +1,325 @@
+//! `Match` blocks, for one document that's a little different on every
+//! machine.
+
+use core::fmt;
+use std::{collections::HashMap, error::Error as StdError, fs};
+
+use crate::{index::KeyIndex, line::Line, Confindent, Value};
+
+/// What `Match` blocks are matched against. See [Confindent::evaluate].
+///
+/// Everything is a fact with a name, like `host` or `env`, and a value.
+/// `host`, `env` and `os` have their own methods because they're the ones
+/// you'll want most.
+///
+/// # Example
+///
+/// ```rust
+/// use confindent::MatchContext;
+///
+/// let context = MatchContext::new()
+/// 	.host("db-1.example.com")
+/// 	.env("prod")
+/// 	.fact("region", "eu-west");
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+pub struct MatchContext {
+	facts: HashMap<String, String>,
+}
+
+/// A `Match` block that couldn't be understood.
+#[derive(Clone, Debug, PartialEq)]
+pub struct MatchError {
+	/// The `/` separated path to the block
+	pub path: String,
+	/// The line the block starts on, starting from zero
+	pub line: Option<usize>,
+	pub kind: MatchErrorKind,
+}
+
+/// What was wrong with the `Match` block.
+#[derive(Clone, Debug, PartialEq)]
+pub enum MatchErrorKind {
+	/// There was nothing after `Match`
+	NoConditions,
+	/// Something after `Match` wasn't a `name=pattern`
+	NotACondition(String),
+}
+
+impl MatchContext {
+	/// A context with only the `os` we're running on, like `linux`.
+	pub fn new() -> Self {
+		Self {
+			facts: HashMap::new(),
+		}
+		.os(std::env::consts::OS)
+	}
+
+	/// Like [new](MatchContext::new), with this machine's `host` too if it
+	/// can be found. It's looked for in `HOSTNAME`, then `/etc/hostname`.
+	pub fn here() -> Self {
+		let host = std::env::var("HOSTNAME")
+			.ok()
+			.or_else(|| fs::read_to_string("/etc/hostname").ok())
+			.map(|host| host.trim().to_owned())
+			.filter(|host| !host.is_empty());
+
+		match host {
+			None => Self::new(),
+			Some(host) => Self::new().host(host),
+		}
+	}
+
+	/// The machine's hostname. It's matched without caring about ASCII case.
+	pub fn host<S: Into<String>>(self, host: S) -> Self {
+		self.fact("host", host)
+	}
+
+	/// The environment, like `prod` or `staging`.
+	pub fn env<S: Into<String>>(self, env: S) -> Self {
+		self.fact("env", env)
+	}
+
+	/// The operating system, named like [std::env::consts::OS] names them.
+	pub fn os<S: Into<String>>(self, os: S) -> Self {
+		self.fact("os", os)
+	}
+
+	/// Any other fact, matched by `name=pattern`. Setting one again
+	/// replaces it.
+	pub fn fact<N: Into<String>, V: Into<String>>(mut self, name: N, value: V) -> Self {
+		self.facts.insert(name.into(), value.into());
+		self
+	}
+
+	/// Whether every condition holds. An unknown fact doesn't match anything.
+	fn matches(&self, conditions: &str) -> Result<bool, MatchErrorKind> {
+		let mut matched = true;
+		let mut any = false;
+
+		for condition in conditions.split_whitespace() {
+			any = true;
+			let Some((name, patterns)) = condition.split_once('=') else {
+				return Err(MatchErrorKind::NotACondition(condition.to_owned()));
+			};
+			if name.is_empty() || patterns.is_empty() {
+				return Err(MatchErrorKind::NotACondition(condition.to_owned()));
+			}
+
+			let holds = self.facts.get(name).is_some_and(|fact| {
+				patterns.split(',').any(|pattern| match name {
+					"host" => glob(&pattern.to_ascii_lowercase(), &fact.to_ascii_lowercase()),
+					_ => glob(pattern, fact),
+				})
+			});
+			matched &= holds;
+		}
+
+		match any {
+			false => Err(MatchErrorKind::NoConditions),
+			true => Ok(matched),
+		}
+	}
+}
+
+impl Default for MatchContext {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+impl Confindent {
+	/// The document as it is in the context, with its `Match` blocks worked
+	/// out.
+	///
+	/// A `Match` block is followed by conditions, like `Match host=db-*
+	/// env=prod`, that all have to hold for it to match. A condition is the
+	/// name of a fact in the context, an `=`, and patterns separated by
+	/// commas, any one of which has to match the fact. In a pattern, `*`
+	/// matches anything and `?` matches any one character.
+	///
+	/// A block that matches is replaced by its children, so they're
+	/// children of whatever it was a child of. One that doesn't is removed,
+	/// children and all. Blocks can be anywhere, even in other blocks.
+	///
+	/// # Example
+	///
+	/// ```rust
+	/// use confindent::{Confindent, MatchContext};
+	///
+	/// let conf: Confindent = "Server\n\tPort 22\n\tMatch env=prod\n\t\tPort 2222\nMatch host=db-*\n\tRole database"
+	/// 	.parse()
+	/// 	.unwrap();
+	///
+	/// let prod = conf.evaluate(&MatchContext::new().host("db-1").env("prod")).unwrap();
+	/// assert_eq!(prod.to_string(), "Server\n\tPort 22\n\tPort 2222\nRole database");
+	/// assert_eq!(prod.children("Server")[0].children("Port").last().unwrap().value(), Some("2222"));
+	///
+	/// let dev = conf.evaluate(&MatchContext::new().host("web-1").env("dev")).unwrap();
+	/// assert_eq!(dev.to_string(), "Server\n\tPort 22");
+	/// ```
+	pub fn evaluate(&self, context: &MatchContext) -> Result<Confindent, MatchError> {
+		let mut conf = self.clone();
+		conf.children = evaluate(std::mem::take(&mut conf.children), &conf.index, "", context)?;
+		conf.index.clear();
+		Ok(conf)
+	}
+}
+
+/// Work out the `Match` blocks in the lines, and in their children.
+fn evaluate(
+	lines: Vec<Line>,
+	index: &KeyIndex,
+	parent: &str,
+	context: &MatchContext,
+) -> Result<Vec<Line>, MatchError> {
+	let mut evaluated = Vec::with_capacity(lines.len());
+
+	for line in lines {
+		let Line::Value(mut value) = line else {
+			evaluated.push(line);
+			continue;
+		};
+
+		let path = match parent {
+			"" => value.key.clone(),
+			parent => format!("{parent}/{}", value.key),
+		};
+		value.children = evaluate(
+			std::mem::take(&mut value.children),
+			&value.index,
+			&path,
+			context,
+		)?;
+		value.index.clear();
+
+		if !index.matches(&value, "Match") {
+			evaluated.push(Line::Value(value));
+			continue;
+		}
+
+		let conditions = value.value.as_deref().unwrap_or_default();
+		let matched = context.matches(conditions).map_err(|kind| MatchError {
+			path,
+			line: value.line,
+			kind,
+		})?;
+		if matched {
+			evaluated.extend(splice(value));
+		}
+	}
+
+	Ok(evaluated)
+}
+
+/// The children of a block, moved out to where the block was.
+fn splice(block: Value) -> impl Iterator<Item = Line> {
+	let indent = block.indent;
+	block.children.into_iter().map(move |mut line| {
+		line.reindent(indent);
+		line
+	})
+}
+
+/// Whether the whole of `s` matches the pattern, where `*` is anything and
+/// `?` is any one character.
+fn glob(pattern: &str, s: &str) -> bool {
+	let (pattern, s): (Vec<char>, Vec<char>) = (pattern.chars().collect(), s.chars().collect());
+	let (mut p, mut i) = (0, 0);
+	// Where the last star was, and where in `s` it's matched up to
+	let mut star = None;
+
+	while i < s.len() {
+		match pattern.get(p) {
+			Some('*') => {
+				star = Some((p, i));
+				p += 1;
+			}
+			Some(&ch) if ch == '?' || ch == s[i] => {
+				p += 1;
+				i += 1;
+			}
+			_ => match star {
+				// Let the last star match one more character and try again
+				Some((star_p, star_i)) => {
+					star = Some((star_p, star_i + 1));
+					p = star_p + 1;
+					i = star_i + 1;
+				}
+				None => return false,
+			},
+		}
+	}
+
+	pattern[p..].iter().all(|ch| *ch == '*')
+}
+
+impl StdError for MatchError {}
+impl fmt::Display for MatchError {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self.line {
+			None => write!(f, "{}: ", self.path)?,
+			Some(line) => write!(f, "{} on line {line}: ", self.path)?,
+		}
+
+		match &self.kind {
+			MatchErrorKind::NoConditions => write!(f, "Match needs at least one condition"),
+			MatchErrorKind::NotACondition(word) => {
+				write!(f, "{word} isn't a condition, which look like name=pattern")
+			}
+		}
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	#[test]
+	fn globs() {
+		assert!(glob("db-*", "db-1"));
+		assert!(glob("db-*", "db-"));
+		assert!(glob("*.example.*", "a.b.example.com"));
+		assert!(glob("db-?", "db-1"));
+		assert!(!glob("db-?", "db-12"));
+		assert!(!glob("db-*", "web-1"));
+		assert!(glob("*", ""));
+	}
+
+	#[test]
+	fn match_blocks() {
+		let text = "# all\nUser gen\nMatch host=DB-* env=prod,staging\n\t# databases\n\tPort 5432\n\tMatch region=eu-*\n\t\tBackup yes\nMatch os=none\n\tPort 1\nServer\n\tmatch region=us\n\t\tPort 2";
+		let conf: Confindent = text.parse().unwrap();
+
+		let context = MatchContext::new()
+			.host("db-2.example.com")
+			.env("staging")
+			.fact("region", "eu-west");
+		let evaluated = conf.evaluate(&context).unwrap();
+		assert_eq!(
+			evaluated.to_string(),
+			"# all\nUser gen\n# databases\nPort 5432\nBackup yes\nServer\n\tmatch region=us\n\t\tPort 2"
+		);
+		assert_eq!(evaluated.get("Port"), Some("5432"));
+
+		// The document itself doesn't change
+		assert_eq!(conf.to_string(), text);
+
+		let err = "A\n\tMatch\n"
+			.parse::<Confindent>()
+			.unwrap()
+			.evaluate(&context);
+		assert_eq!(
+			err.unwrap_err().to_string(),
+			"A/Match on line 1: Match needs at least one condition"
+		);
+		let err = "Match prod"
+			.parse::<Confindent>()
+			.unwrap()
+			.evaluate(&context);
+		assert_eq!(
+			err.unwrap_err().kind,
+			MatchErrorKind::NotACondition("prod".into())
+		);
+	}
+}
@@ -0,0 +1,351 @@
+//! Changing how documents are read.
+
+use crate::{error::ParseErrorKind, indent::Indent, Value};
+
+/// How a document is read, for files that are almost, but not quite,
+/// written the way we'd write them.
+///
+/// The default reads a document the same way parsing one always does: a
+/// `#` starts a comment, a single space separates a key from its value,
+/// either tabs or spaces can indent, and a key followed by a space and
+/// nothing else doesn't have a value.
+///
+/// However a document is read, printing it gives back exactly what was
+/// read. Values you add are separated from their keys the same way as the
+/// values next to them.
+///
+/// # Example
+///
+/// ```rust
+/// use confindent::{AllowedIndent, Confindent, ParseOptions, Separator};
+///
+/// let options = ParseOptions::new()
+/// 	.comments(["#", ";", "//"])
+/// 	.separator(Separator::Char('='))
+/// 	.indent(AllowedIndent::Spaces);
+///
+/// let text = "; the server\nHost = example.com\n  // the default\n  Port = 22\n";
+/// let conf = Confindent::from_str_with(text, &options).unwrap();
+///
+/// assert_eq!(conf.get("Host/Port"), Some("22"));
+/// assert_eq!(conf.to_string(), text);
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+pub struct ParseOptions {
+	comments: Vec<String>,
+	separator: Separator,
+	indent: AllowedIndent,
+	empty_values: bool,
+	pub(crate) strict_dedent: bool,
+	/// How wide a tab is, if tabs and spaces can be mixed
+	pub(crate) tab_width: Option<usize>,
+}
+
+/// What separates a key from its value. See [ParseOptions::separator].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Separator {
+	/// The first space. Anything after it, even more spaces, is the value.
+	#[default]
+	Space,
+	/// The first run of spaces and tabs, so `Key\tValue` works too.
+	Whitespace,
+	/// The first of this character, and any spaces or tabs around it, so
+	/// `Key = Value` is `Key` and `Value`. Keys can have spaces in them.
+	Char(char),
+}
+
+/// What a document can be indented with. See [ParseOptions::indent].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum AllowedIndent {
+	/// Tabs or spaces, but not both in the same block.
+	#[default]
+	Either,
+	/// Only tabs. Spaces are a [SpacesNotAllowed](ParseErrorKind::SpacesNotAllowed)
+	/// error.
+	Tabs,
+	/// Only spaces. Tabs are a [TabsNotAllowed](ParseErrorKind::TabsNotAllowed)
+	/// error.
+	Spaces,
+}
+
+impl Default for ParseOptions {
+	fn default() -> Self {
+		Self {
+			comments: vec!["#".into()],
+			separator: Separator::Space,
+			indent: AllowedIndent::Either,
+			empty_values: false,
+			strict_dedent: false,
+			tab_width: None,
+		}
+	}
+}
+
+impl ParseOptions {
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	/// What starts a comment, instead of just `#`. A line that starts with
+	/// any of them, after its indent, is a comment. They're checked in order,
+	/// so put longer ones first if one starts another.
+	pub fn comments<I, S>(mut self, prefixes: I) -> Self
+	where
+		I: IntoIterator<Item = S>,
+		S: Into<String>,
+	{
+		self.comments = prefixes.into_iter().map(Into::into).collect();
+		self
+	}
+
+	/// What separates a key from its value.
+	pub fn separator(mut self, separator: Separator) -> Self {
+		self.separator = separator;
+		self
+	}
+
+	/// What the document can be indented with.
+	pub fn indent(mut self, indent: AllowedIndent) -> Self {
+		self.indent = indent;
+		self
+	}
+
+	/// Whether a key followed by a separator, but nothing after it, has an
+	/// empty value instead of none at all.
+	///
+	/// # Example
+	///
+	/// ```rust
+	/// use confindent::{Confindent, ParseOptions};
+	///
+	/// let options = ParseOptions::new().empty_values(true);
+	/// let conf = Confindent::from_str_with("Empty \nNone", &options).unwrap();
+	///
+	/// assert_eq!(conf.child_value("Empty"), Some(""));
+	/// assert_eq!(conf.child_value("None"), None);
+	/// ```
+	pub fn empty_values(mut self, empty: bool) -> Self {
+		self.empty_values = empty;
+		self
+	}
+
+	/// Whether a value that's less indented than the one before it has to
+	/// line up with one of the values it's in. Comments don't, since they
+	/// can't end a block.
+	///
+	/// Without this, a line that doesn't line up with anything is a child
+	/// of the deepest value, even though it's less indented than it. That
+	/// will be an error by default in the next major version.
+	///
+	/// # Example
+	///
+	/// ```rust
+	/// use confindent::{Confindent, ParseError, ParseErrorKind, ParseOptions};
+	///
+	/// let text = "Key\n    A\n  B b";
+	///
+	/// // B ends up a child of A
+	/// let conf: Confindent = text.parse().unwrap();
+	/// assert_eq!(conf.get("Key/A/B"), Some("b"));
+	///
+	/// let strict = ParseOptions::new().strict_dedent(true);
+	/// assert_eq!(
+	/// 	Confindent::from_str_with(text, &strict),
+	/// 	Err(ParseError {
+	/// 		line: 2,
+	/// 		kind: ParseErrorKind::InconsistentDedent { block: 1 }
+	/// 	})
+	/// );
+	/// ```
+	pub fn strict_dedent(mut self, strict: bool) -> Self {
+		self.strict_dedent = strict;
+		self
+	}
+
+	/// Read documents that mix tabs and spaces, counting a tab as reaching
+	/// the next multiple of `tab_width` columns. A width of zero is taken
+	/// as one.
+	///
+	/// Lines line up by the column they reach instead of by their exact
+	/// indent, so a block can be indented with tabs on one line and spaces
+	/// on the next. Rather than failing, every line that would have been an
+	/// error is kept in the document's [warnings](crate::Confindent::warnings)
+	/// as that error. Lines are printed with the indent they were written
+	/// with until [normalize](crate::Confindent::normalize) cleans them up.
+	///
+	/// # Example
+	///
+	/// ```rust
+	/// use confindent::{Confindent, ParseErrorKind, ParseOptions};
+	///
+	/// let text = "Host\n\tUser gen\n    Port 22\n";
+	/// assert!(text.parse::<Confindent>().is_err());
+	///
+	/// let tolerant = ParseOptions::new().mixed_indent(4);
+	/// let mut conf = Confindent::from_str_with(text, &tolerant).unwrap();
+	///
+	/// assert_eq!(conf.get("Host/Port"), Some("22"));
+	/// assert_eq!(conf.warnings()[0].line, 2);
+	/// assert_eq!(conf.warnings()[0].kind, ParseErrorKind::SpacesWithTabs);
+	/// assert_eq!(conf.to_string(), text);
+	///
+	/// conf.normalize();
+	/// assert_eq!(conf.to_string(), "Host\n\tUser gen\n\tPort 22\n");
+	/// ```
+	pub fn mixed_indent(mut self, tab_width: usize) -> Self {
+		self.tab_width = Some(tab_width.max(1));
+		self
+	}
+
+	/// The prefix and the text of the comment, if the line is one.
+	pub(crate) fn comment<'a>(&self, line: &'a str) -> Option<(&'a str, &'a str)> {
+		self.comments
+			.iter()
+			.filter(|prefix| !prefix.is_empty())
+			.find_map(|prefix| line.strip_prefix(prefix.as_str()))
+			.map(|comment| line.split_at(line.len() - comment.len()))
+	}
+
+	pub(crate) fn check_indent(&self, indent: &Indent) -> Result<(), ParseErrorKind> {
+		match (self.indent, indent) {
+			(AllowedIndent::Tabs, Indent::Spaces { .. }) => Err(ParseErrorKind::SpacesNotAllowed),
+			(AllowedIndent::Spaces, Indent::Tabs { .. }) => Err(ParseErrorKind::TabsNotAllowed),
+			_ => Ok(()),
+		}
+	}
+
+	/// Split a line, without its indent, into the key, the value, and the
+	/// separator if it has to be remembered.
+	pub(crate) fn split_key_value<'a>(
+		&self,
+		line: &'a str,
+	) -> (&'a str, Option<&'a str>, Option<&'a str>) {
+		let is_space = |c: char| c == ' ' || c == '\t';
+
+		let (key, separator, value) = match self.separator {
+			Separator::Space => match Value::split_key_value(line) {
+				(key, None, Some(separator)) if self.empty_values => (key, separator, ""),
+				split => return split,
+			},
+			Separator::Whitespace => match line.find(is_space) {
+				None => (line, "", ""),
+				Some(at) => {
+					let (key, rest) = line.split_at(at);
+					let value = rest.trim_start_matches(is_space);
+					(key, &rest[..rest.len() - value.len()], value)
+				}
+			},
+			Separator::Char(c) => match line.find(c) {
+				None => {
+					let key = line.trim_end_matches(is_space);
+					(key, &line[key.len()..], "")
+				}
+				Some(at) => {
+					let key = line[..at].trim_end_matches(is_space);
+					let rest = &line[at + c.len_utf8()..];
+					let value = rest.trim_start_matches(is_space);
+					let separator_end = line.len() - value.len();
+					(key, &line[key.len()..separator_end], value)
+				}
+			},
+		};
+
+		let value = match value {
+			"" if !self.empty_values || separator.is_empty() => None,
+			value => Some(value),
+		};
+		let separator = match (separator, value) {
+			(" ", Some(_)) | ("", None) => None,
+			(separator, _) => Some(separator),
+		};
+
+		(key, value, separator)
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use crate::{Confindent, ConfindentRef, Event, ParseError, Parser, Value};
+
+	use super::*;
+
+	#[test]
+	fn separators() {
+		let whitespace = ParseOptions::new().separator(Separator::Whitespace);
+		let text = "Key\tValue\nSpaced   out value\nAlone\nTrailing\t\n";
+		let mut conf = Confindent::from_str_with(text, &whitespace).unwrap();
+
+		assert_eq!(conf.child_value("Key"), Some("Value"));
+		assert_eq!(conf.child_value("Spaced"), Some("out value"));
+		assert_eq!(conf.child_value("Trailing"), None);
+		assert_eq!(conf.to_string(), text);
+
+		// Added values look like the ones around them
+		conf.add_child(Value::new("Added", Some("yes")));
+		assert!(conf.to_string().ends_with("Added\tyes\n"));
+
+		let colon = ParseOptions::new().separator(Separator::Char(':'));
+		let text = "A key: a: value\nEmpty:\nNone\n\tChild :x\n";
+		let conf = Confindent::from_str_with(text, &colon).unwrap();
+
+		assert_eq!(conf.child_value("A key"), Some("a: value"));
+		assert_eq!(conf.child_value("Empty"), None);
+		assert_eq!(conf.get("None/Child"), Some("x"));
+		assert_eq!(conf.to_string(), text);
+
+		let empty = colon.empty_values(true);
+		let conf = Confindent::from_str_with(text, &empty).unwrap();
+		assert_eq!(conf.child_value("Empty"), Some(""));
+		assert_eq!(conf.child_value("None"), None);
+		assert_eq!(conf.to_string(), text);
+	}
+
+	#[test]
+	fn comments() {
+		let options = ParseOptions::new().comments(["//", "/", ";"]);
+		let text = "// slashes\n/ slash\n; semicolon\n# not a comment\n";
+		let conf = Confindent::from_str_with(text, &options).unwrap();
+
+		assert_eq!(conf.values().count(), 1);
+		assert_eq!(conf.child_value("#"), Some("not a comment"));
+		assert_eq!(conf.to_string(), text);
+
+		let borrowed = ConfindentRef::parse_with(text, &options).unwrap();
+		assert_eq!(borrowed.into_owned(), conf);
+
+		let events: Vec<Event> = Parser::with_options(text.as_bytes(), &options)
+			.take(1)
+			.collect::<Result<_, _>>()
+			.unwrap();
+		assert_eq!(
+			events,
+			[Event::Comment {
+				comment: " slashes".into(),
+				depth: 0,
+				line: 0
+			}]
+		);
+	}
+
+	#[test]
+	fn allowed_indent() {
+		let tabs = ParseOptions::new().indent(AllowedIndent::Tabs);
+		assert!(Confindent::from_str_with("A\n\tB\n", &tabs).is_ok());
+		assert_eq!(
+			Confindent::from_str_with("A\n\tB\n  C", &tabs),
+			Err(ParseError {
+				line: 2,
+				kind: ParseErrorKind::SpacesNotAllowed
+			})
+		);
+
+		let spaces = ParseOptions::new().indent(AllowedIndent::Spaces);
+		assert_eq!(
+			Confindent::from_str_with("A\n\t# B", &spaces),
+			Err(ParseError {
+				line: 1,
+				kind: ParseErrorKind::TabsNotAllowed
+			})
+		);
+	}
+}
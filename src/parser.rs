@@ -0,0 +1,887 @@
+use std::io::BufRead;
+
+use crate::{
+	error::{ParseError, ParseErrorKind},
+	indent::Indent,
+	index::KeyIndex,
+	line::{Line, LineEnding},
+	ParseOptions, Value,
+};
+
+/// Something that happened while reading a document with a [Parser].
+///
+/// Every [StartNode](Event::StartNode) is eventually followed by an
+/// [EndNode](Event::EndNode). The events between the two are that value's
+/// children. Line numbers start at zero, like they do in [ParseError].
+#[derive(Clone, Debug, PartialEq)]
+pub enum Event {
+	/// A value was found. Its children follow until the matching
+	/// [EndNode](Event::EndNode).
+	StartNode {
+		key: String,
+		value: Option<String>,
+		depth: usize,
+		line: usize,
+	},
+	/// The most recent value that hasn't ended yet has no more children.
+	EndNode,
+	/// A comment, without what started it. Its depth is where its indent
+	/// puts it, but comments don't end values, so it can be shallower than
+	/// the values still open around it.
+	Comment {
+		comment: String,
+		depth: usize,
+		line: usize,
+	},
+	/// A line with nothing but whitespace.
+	Blank { depth: usize, line: usize },
+}
+
+/// A line split into its parts, borrowing from the text it came from.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) enum RawLine<'a> {
+	Value {
+		indent: Indent,
+		/// The indent as it was written, if it mixed tabs and spaces
+		written_indent: Option<&'a str>,
+		key: &'a str,
+		value: Option<&'a str>,
+		separator: Option<&'a str>,
+		ending: Option<LineEnding>,
+		number: usize,
+	},
+	Comment {
+		indent: Indent,
+		/// The indent as it was written, if it mixed tabs and spaces
+		written_indent: Option<&'a str>,
+		/// What started the comment if it wasn't a `#`
+		prefix: Option<&'a str>,
+		comment: &'a str,
+		ending: Option<LineEnding>,
+	},
+	Blank {
+		whitespace: &'a str,
+		ending: Option<LineEnding>,
+	},
+}
+
+impl<'a> RawLine<'a> {
+	fn split(
+		raw: &'a str,
+		ending: Option<LineEnding>,
+		number: usize,
+		options: &ParseOptions,
+	) -> Result<(Self, Option<ParseErrorKind>), ParseErrorKind> {
+		if blank_line(raw) {
+			let blank = RawLine::Blank {
+				whitespace: raw,
+				ending,
+			};
+			return Ok((blank, None));
+		}
+
+		// With mixed_indent, tabs and spaces in the same indent are read as
+		// the spaces it takes to reach the same column. They're still written
+		// the way they were
+		let mut tolerated = None;
+		let mut written_indent = None;
+		let (indent, other) = match (Value::split_whitespace(raw), options.tab_width) {
+			(Err(ParseErrorKind::MixedIndent), Some(tab_width)) => {
+				tolerated = Some(ParseErrorKind::MixedIndent);
+				let (indent, other) = raw.split_at(Value::whitespace_end_index(raw));
+				written_indent = Some(indent);
+				(Indent::from_mixed(indent, tab_width), other)
+			}
+			(split, _) => split?,
+		};
+		options.check_indent(&indent)?;
+
+		let line = if let Some((prefix, comment)) = options.comment(other) {
+			RawLine::Comment {
+				indent,
+				written_indent,
+				prefix: Some(prefix).filter(|prefix| *prefix != "#"),
+				comment,
+				ending,
+			}
+		} else {
+			let (key, value, separator) = options.split_key_value(other);
+			RawLine::Value {
+				indent,
+				written_indent,
+				key,
+				value,
+				separator,
+				ending,
+				number,
+			}
+		};
+
+		Ok((line, tolerated))
+	}
+
+	fn indent_mut(&mut self) -> Option<&mut Indent> {
+		match self {
+			RawLine::Value { indent, .. } | RawLine::Comment { indent, .. } => Some(indent),
+			RawLine::Blank { .. } => None,
+		}
+	}
+}
+
+impl From<RawLine<'_>> for Line {
+	fn from(raw: RawLine<'_>) -> Self {
+		match raw {
+			RawLine::Value {
+				indent,
+				written_indent,
+				key,
+				value,
+				separator,
+				ending,
+				number,
+			} => Line::Value(Value {
+				indent,
+				written_indent: written_indent.map(<_>::to_owned),
+				key: key.to_owned(),
+				value: value.map(<_>::to_owned),
+				separator: separator.map(<_>::to_owned),
+				ending,
+				line: Some(number),
+				children: vec![],
+				index: KeyIndex::default(),
+				secret: false,
+				inherited: false,
+			}),
+			RawLine::Comment {
+				indent,
+				written_indent,
+				prefix,
+				comment,
+				ending,
+			} => Line::Comment {
+				indent,
+				written_indent: written_indent.map(<_>::to_owned),
+				prefix: prefix.map(<_>::to_owned),
+				comment: comment.to_owned(),
+				ending,
+			},
+			RawLine::Blank { whitespace, ending } => Line::Blank {
+				whitespace: whitespace.to_owned(),
+				ending,
+			},
+		}
+	}
+}
+
+/// A line and where in the tree it belongs.
+pub(crate) struct Placed<T> {
+	pub(crate) line: T,
+	pub(crate) depth: usize,
+	/// How many values ended before this line
+	pub(crate) ended: usize,
+	pub(crate) number: usize,
+}
+
+impl<T> Placed<T> {
+	pub(crate) fn map<U, F: FnOnce(T) -> U>(self, f: F) -> Placed<U> {
+		Placed {
+			line: f(self.line),
+			depth: self.depth,
+			ended: self.ended,
+			number: self.number,
+		}
+	}
+}
+
+impl Placed<Line> {
+	fn into_event(self) -> Event {
+		let Placed {
+			line,
+			depth,
+			number,
+			..
+		} = self;
+
+		match line {
+			Line::Value(value) => Event::StartNode {
+				key: value.key,
+				value: value.value,
+				depth,
+				line: number,
+			},
+			Line::Comment { comment, .. } => Event::Comment {
+				comment,
+				depth,
+				line: number,
+			},
+			Line::Blank { .. } => Event::Blank {
+				depth,
+				line: number,
+			},
+		}
+	}
+}
+
+/// Something a [TreeBuilder] can build a tree of.
+pub(crate) trait Node: Sized {
+	type Line: From<Self>;
+
+	/// Turn the line into a node if it's a value, otherwise give it back.
+	fn from_line(line: Self::Line) -> Result<Self, Self::Line>;
+	fn children_mut(&mut self) -> &mut Vec<Self::Line>;
+}
+
+impl Node for Value {
+	type Line = Line;
+
+	fn from_line(line: Line) -> Result<Self, Line> {
+		match line {
+			Line::Value(value) => Ok(value),
+			other => Err(other),
+		}
+	}
+
+	fn children_mut(&mut self) -> &mut Vec<Line> {
+		&mut self.children
+	}
+}
+
+/// Builds a tree from [Placed] lines.
+///
+/// Every value that hasn't ended yet is kept on a stack, deepest last, so
+/// a line goes right where it belongs without looking through the tree.
+/// Values are added to their parent when they end.
+///
+/// Comments don't end values, so one that's less indented than the value
+/// before it waits for the next value to find out whether that value's
+/// block ended. If it did, the comment goes at its own depth. If it didn't,
+/// the comment stays in the block so the lines keep their order.
+pub(crate) struct TreeBuilder<N: Node> {
+	root: Vec<N::Line>,
+	open: Vec<N>,
+	/// Lines waiting for the next value, and the depth they'd like to be at
+	waiting: Vec<(N::Line, usize)>,
+}
+
+impl<N: Node> TreeBuilder<N> {
+	pub(crate) fn new() -> Self {
+		Self {
+			root: vec![],
+			open: vec![],
+			waiting: vec![],
+		}
+	}
+
+	pub(crate) fn push(&mut self, placed: Placed<N::Line>) {
+		match N::from_line(placed.line) {
+			Ok(node) => {
+				self.settle(self.open.len() - placed.ended);
+				self.open.push(node);
+			}
+			Err(line) if self.waiting.is_empty() && placed.depth >= self.open.len() => {
+				self.deepest().push(line)
+			}
+			Err(line) => self.waiting.push((line, placed.depth)),
+		}
+	}
+
+	pub(crate) fn finish(mut self) -> Vec<N::Line> {
+		self.settle(0);
+		self.root
+	}
+
+	/// End values until only `keep` are open, putting the waiting lines as
+	/// close to their depth as they can go without changing their order.
+	fn settle(&mut self, keep: usize) {
+		for (line, depth) in std::mem::take(&mut self.waiting) {
+			let depth = depth.clamp(keep, self.open.len());
+			while self.open.len() > depth {
+				self.close();
+			}
+			self.deepest().push(line);
+		}
+
+		while self.open.len() > keep {
+			self.close();
+		}
+	}
+
+	fn close(&mut self) {
+		if let Some(node) = self.open.pop() {
+			self.deepest().push(node.into());
+		}
+	}
+
+	fn deepest(&mut self) -> &mut Vec<N::Line> {
+		match self.open.last_mut() {
+			None => &mut self.root,
+			Some(node) => node.children_mut(),
+		}
+	}
+}
+
+/// Keeps track of which values are open while reading a document line by
+/// line, and so where each new line belongs.
+pub(crate) struct Nesting {
+	/// The number of the next line
+	line_number: usize,
+	/// The indent, and line number, of every value that has not yet ended,
+	/// shallowest first
+	open: Vec<(Indent, usize)>,
+	pub(crate) bom: bool,
+	/// The ending of the first line
+	pub(crate) line_ending: Option<LineEnding>,
+	/// Whether the last line had a line ending
+	pub(crate) final_newline: bool,
+	/// Lines that would have been errors without
+	/// [mixed_indent](ParseOptions::mixed_indent)
+	pub(crate) warnings: Vec<ParseError>,
+	options: ParseOptions,
+}
+
+impl Nesting {
+	pub(crate) fn new(options: ParseOptions) -> Self {
+		Self {
+			line_number: 0,
+			open: vec![],
+			bom: false,
+			line_ending: None,
+			final_newline: true,
+			warnings: vec![],
+			options,
+		}
+	}
+
+	/// End the deepest open value, returning false if there wasn't one.
+	fn close(&mut self) -> bool {
+		self.open.pop().is_some()
+	}
+
+	/// Split the next line, ending included, and find where it belongs.
+	pub(crate) fn line<'a>(&mut self, mut raw: &'a str) -> Result<Placed<RawLine<'a>>, ParseError> {
+		let number = self.line_number;
+		self.line_number += 1;
+
+		if number == 0 {
+			if let Some(stripped) = raw.strip_prefix('\u{feff}') {
+				self.bom = true;
+				raw = stripped;
+			}
+		}
+
+		let (raw, ending, _) = LineEnding::split_line(raw);
+		self.final_newline = ending.is_some();
+		if number == 0 {
+			self.line_ending = ending;
+		}
+
+		// We only keep line endings that differ from the document's
+		let ending = ending.filter(|ending| Some(*ending) != self.line_ending);
+
+		let placed =
+			RawLine::split(raw, ending, number, &self.options).and_then(|(mut line, mixed)| {
+				let (depth, mismatched) = self.place(&mut line)?;
+				Ok((line, depth, mixed.or(mismatched)))
+			});
+
+		let (line, depth, tolerated) = placed.map_err(|kind| {
+			self.open.clear();
+			ParseError { line: number, kind }
+		})?;
+
+		if let Some(kind) = tolerated {
+			self.warnings.push(ParseError { line: number, kind });
+		}
+
+		let ended = match &line {
+			// Only values end values. A less indented comment doesn't, so a
+			// value after it that's indented deeper is still in the same block
+			RawLine::Blank { .. } | RawLine::Comment { .. } => 0,
+			RawLine::Value { indent, .. } => {
+				let ended = self.open.len() - depth;
+				self.open.truncate(depth);
+				self.open.push((*indent, number));
+				ended
+			}
+		};
+
+		Ok(Placed {
+			line,
+			depth,
+			ended,
+			number,
+		})
+	}
+
+	/// Find the depth of a line, and the error it would have been if we
+	/// tolerated tabs and spaces being mixed. See [place_exactly] and
+	/// [place_by_column].
+	///
+	/// [place_exactly]: Nesting::place_exactly
+	/// [place_by_column]: Nesting::place_by_column
+	fn place(&self, line: &mut RawLine) -> Result<(usize, Option<ParseErrorKind>), ParseErrorKind> {
+		match (self.place_exactly(line), self.options.tab_width) {
+			(
+				Err(kind @ (ParseErrorKind::TabsWithSpaces | ParseErrorKind::SpacesWithTabs)),
+				Some(tab_width),
+			) => {
+				let depth = self.place_by_column(line, tab_width)?;
+				Ok((depth, Some(kind)))
+			}
+			(placed, _) => placed.map(|depth| (depth, None)),
+		}
+	}
+
+	/// Find the depth of a line and fill in its indent's delta.
+	///
+	/// A line belongs alongside the shallowest open value with the same
+	/// indent. If there isn't one, it's a child of the deepest. This only
+	/// looks at the open values, so it takes as long as the document is
+	/// deep, not as long as it is wide.
+	fn place_exactly(&self, line: &mut RawLine) -> Result<usize, ParseErrorKind> {
+		// Comments don't change the nesting, so they don't have to line up
+		let strict = self.options.strict_dedent && matches!(line, RawLine::Value { .. });
+		let indent = match line.indent_mut() {
+			None => return Ok(self.open.len()),
+			Some(indent) => indent,
+		};
+
+		if *indent == Indent::Empty {
+			return Ok(0);
+		} else if self.open.is_empty() {
+			return Err(ParseErrorKind::StartedIndented);
+		}
+
+		for depth in 1..self.open.len() {
+			let (open, _) = &self.open[depth];
+
+			match (*indent, open) {
+				(Indent::Empty, _) | (_, Indent::Empty) => unreachable!(),
+				(Indent::Tabs { .. }, Indent::Spaces { .. }) => {
+					return Err(ParseErrorKind::TabsWithSpaces)
+				}
+				(Indent::Spaces { .. }, Indent::Tabs { .. }) => {
+					return Err(ParseErrorKind::SpacesWithTabs)
+				}
+				(
+					Indent::Tabs { count, .. },
+					Indent::Tabs {
+						count: open_count, ..
+					},
+				)
+				| (
+					Indent::Spaces { count, .. },
+					Indent::Spaces {
+						count: open_count, ..
+					},
+				) => {
+					if count == *open_count {
+						indent.delta_from(open)?;
+						return Ok(depth);
+					}
+				}
+			}
+		}
+
+		let deepest = self.open.len();
+		if strict {
+			// Anything less indented than the deepest value has to line up
+			// with one of the values it's in, and it didn't
+			let count = indent.count();
+			let deeper = self.open[1..].iter().find(|(open, _)| open.count() > count);
+			if let Some((_, block)) = deeper {
+				return Err(ParseErrorKind::InconsistentDedent { block: *block });
+			}
+		}
+
+		if let Indent::Tabs { .. } = indent {
+			indent.delta_from(&self.open[deepest - 1].0)?;
+		}
+
+		Ok(deepest)
+	}
+
+	/// Like [place_exactly](Nesting::place_exactly), but lining lines up by
+	/// the column they reach, for when tabs and spaces are mixed. The line
+	/// keeps the indent it was written with, so the document prints the way
+	/// it was read until it's [normalized](crate::Confindent::normalize).
+	fn place_by_column(
+		&self,
+		line: &mut RawLine,
+		tab_width: usize,
+	) -> Result<usize, ParseErrorKind> {
+		let strict = self.options.strict_dedent && matches!(line, RawLine::Value { .. });
+		// place_exactly only errors like this for indented lines
+		let Some(indent) = line.indent_mut() else {
+			return Ok(self.open.len());
+		};
+		let column = indent.columns(tab_width);
+
+		for depth in 1..self.open.len() {
+			let (open, _) = &self.open[depth];
+
+			if open.columns(tab_width) == column {
+				indent.delta_by_column(open, tab_width);
+				return Ok(depth);
+			}
+		}
+
+		let deepest = self.open.len();
+		if strict {
+			let deeper = self.open[1..]
+				.iter()
+				.find(|(open, _)| open.columns(tab_width) > column);
+			if let Some((_, block)) = deeper {
+				return Err(ParseErrorKind::InconsistentDedent { block: *block });
+			}
+		}
+
+		let (parent, _) = &self.open[deepest - 1];
+		indent.delta_by_column(parent, tab_width);
+
+		Ok(deepest)
+	}
+}
+
+/// Reads a document a line at a time without building the tree.
+///
+/// This is useful for documents too large to want to hold in memory. It
+/// follows the same rules, and returns the same errors, as parsing a
+/// [Confindent](crate::Confindent).
+///
+/// # Example
+///
+/// ```rust
+/// use confindent::{Event, Parser};
+///
+/// let conf = "Host example.com\n\tPort 22\n";
+/// let events: Vec<Event> = Parser::new(conf.as_bytes())
+/// 	.collect::<Result<_, _>>()
+/// 	.unwrap();
+///
+/// assert_eq!(
+/// 	events,
+/// 	vec![
+/// 		Event::StartNode {
+/// 			key: "Host".into(),
+/// 			value: Some("example.com".into()),
+/// 			depth: 0,
+/// 			line: 0
+/// 		},
+/// 		Event::StartNode {
+/// 			key: "Port".into(),
+/// 			value: Some("22".into()),
+/// 			depth: 1,
+/// 			line: 1
+/// 		},
+/// 		Event::EndNode,
+/// 		Event::EndNode,
+/// 	]
+/// );
+/// ```
+pub struct Parser<R: BufRead> {
+	reader: R,
+	buffer: String,
+	pub(crate) nesting: Nesting,
+	/// Set once we've read everything or hit an error
+	done: bool,
+	/// Values that ended and still need an [Event::EndNode]
+	ended: usize,
+	/// An event waiting for its [Event::EndNode]s to go out first
+	waiting: Option<Event>,
+}
+
+impl<R: BufRead> Parser<R> {
+	pub fn new(reader: R) -> Self {
+		Self::with_options(reader, &ParseOptions::default())
+	}
+
+	/// Read a document the way the options say to. See [ParseOptions].
+	pub fn with_options(reader: R, options: &ParseOptions) -> Self {
+		Self {
+			reader,
+			buffer: String::new(),
+			nesting: Nesting::new(options.clone()),
+			done: false,
+			ended: 0,
+			waiting: None,
+		}
+	}
+
+	/// The lines read so far that were tolerated by
+	/// [mixed_indent](ParseOptions::mixed_indent). See
+	/// [Confindent::warnings](crate::Confindent::warnings).
+	pub fn warnings(&self) -> &[ParseError] {
+		&self.nesting.warnings
+	}
+
+	/// Read the next line and figure out where it goes.
+	pub(crate) fn next_line(&mut self) -> Option<Result<Placed<Line>, ParseError>> {
+		if self.done {
+			return None;
+		}
+
+		self.buffer.clear();
+		match self.reader.read_line(&mut self.buffer) {
+			Ok(0) => {
+				self.done = true;
+				return None;
+			}
+			Ok(_) => (),
+			Err(_) => {
+				self.done = true;
+				self.nesting.open.clear();
+				return Some(Err(ParseError {
+					line: self.nesting.line_number,
+					kind: ParseErrorKind::FileReadError,
+				}));
+			}
+		}
+
+		match self.nesting.line(&self.buffer) {
+			Err(e) => {
+				self.done = true;
+				Some(Err(e))
+			}
+			Ok(placed) => Some(Ok(placed.map(Line::from))),
+		}
+	}
+}
+
+impl<R: BufRead> Iterator for Parser<R> {
+	type Item = Result<Event, ParseError>;
+
+	fn next(&mut self) -> Option<Self::Item> {
+		if self.ended > 0 {
+			self.ended -= 1;
+			return Some(Ok(Event::EndNode));
+		} else if let Some(event) = self.waiting.take() {
+			return Some(Ok(event));
+		}
+
+		match self.next_line() {
+			Some(Err(e)) => Some(Err(e)),
+			Some(Ok(placed)) => {
+				self.ended = placed.ended;
+				self.waiting = Some(placed.into_event());
+				self.next()
+			}
+			// Everything still open ends with the document
+			None if self.nesting.close() => Some(Ok(Event::EndNode)),
+			None => None,
+		}
+	}
+}
+
+fn blank_line(s: &str) -> bool {
+	for ch in s.chars() {
+		if !ch.is_whitespace() {
+			return false;
+		}
+	}
+	true
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use crate::{Confindent, ConfindentRef};
+
+	fn events(s: &str) -> Vec<Event> {
+		Parser::new(s.as_bytes()).collect::<Result<_, _>>().unwrap()
+	}
+
+	fn start(key: &str, depth: usize, line: usize) -> Event {
+		Event::StartNode {
+			key: key.into(),
+			value: None,
+			depth,
+			line,
+		}
+	}
+
+	#[test]
+	fn nested_and_siblings() {
+		let conf = "A\n\tB\n\t\tC\n\tD\nE";
+
+		assert_eq!(
+			events(conf),
+			vec![
+				start("A", 0, 0),
+				start("B", 1, 1),
+				start("C", 2, 2),
+				Event::EndNode,
+				Event::EndNode,
+				start("D", 1, 3),
+				Event::EndNode,
+				Event::EndNode,
+				start("E", 0, 4),
+				Event::EndNode,
+			]
+		);
+	}
+
+	#[test]
+	fn comments_and_blanks() {
+		let conf = "A\n\tB\n\n\t# comment\nC";
+
+		assert_eq!(
+			events(conf),
+			vec![
+				start("A", 0, 0),
+				start("B", 1, 1),
+				Event::Blank { depth: 2, line: 2 },
+				Event::Comment {
+					comment: " comment".into(),
+					depth: 1,
+					line: 3
+				},
+				Event::EndNode,
+				Event::EndNode,
+				start("C", 0, 4),
+				Event::EndNode,
+			]
+		);
+	}
+
+	#[test]
+	fn comments_dont_end_blocks() {
+		for (text, expected) in [
+			(
+				"A\n\tB\n\t\tC\n\t# comment\n\t\tD\n",
+				"A\n\tB\n\t\tC\n\t\t# comment\n\t\tD\n",
+			),
+			(
+				"A\n  B\n    C\n  # comment\n    D\n",
+				"A\n\tB\n\t\tC\n\t\t# comment\n\t\tD\n",
+			),
+		] {
+			let mut conf: Confindent = text.parse().unwrap();
+			assert!(conf.child("A").unwrap().child("B").unwrap().has_child("D"));
+			let borrowed = ConfindentRef::parse(text).unwrap();
+			assert!(borrowed
+				.child("A")
+				.unwrap()
+				.child("B")
+				.unwrap()
+				.has_child("D"));
+			assert_eq!(conf.to_string(), text);
+
+			// The comment is in B with D, so it moves with them
+			conf.normalize();
+			assert_eq!(conf.to_string(), expected);
+
+			// The comment doesn't end anything, and D is still in B
+			let events = events(text);
+			let comment = events
+				.iter()
+				.position(|event| matches!(event, Event::Comment { line: 3, .. }))
+				.unwrap();
+			assert!(matches!(events[comment], Event::Comment { depth: 1, .. }));
+			assert_eq!(
+				events[comment + 1..comment + 3],
+				[Event::EndNode, start("D", 2, 4)]
+			);
+		}
+
+		// When the block does end, the comment goes with what's after it
+		let conf: Confindent = "A\n\tB\n\t\tC\n\t# comment\n\tD\n".parse().unwrap();
+		let a = conf.child("A").unwrap();
+		assert_eq!(a.to_string(), "A\n\tB\n\t\tC\n\t# comment\n\tD\n");
+		assert!(matches!(a.children[1], Line::Comment { .. }));
+	}
+
+	#[test]
+	fn errors_stop_parsing() {
+		let mut parser = Parser::new("A\n\tB\n    C\nD".as_bytes());
+
+		assert!(matches!(parser.next(), Some(Ok(Event::StartNode { .. }))));
+		assert!(matches!(parser.next(), Some(Ok(Event::StartNode { .. }))));
+		assert_eq!(
+			parser.next(),
+			Some(Err(ParseError {
+				line: 2,
+				kind: ParseErrorKind::SpacesWithTabs
+			}))
+		);
+		assert_eq!(parser.next(), None);
+	}
+
+	#[test]
+	fn strict_dedent() {
+		let strict = ParseOptions::new().strict_dedent(true);
+		let error = |text: &str| {
+			Parser::with_options(text.as_bytes(), &strict)
+				.find_map(Result::err)
+				.map(|e| (e.line, e.kind))
+		};
+
+		assert_eq!(
+			error("K\n    A\n        B\n    C\nD\n\tE\n\t\tF\n\tG"),
+			None
+		);
+		assert_eq!(
+			error("K\n    A\n  B"),
+			Some((2, ParseErrorKind::InconsistentDedent { block: 1 }))
+		);
+		assert_eq!(
+			error("K\n    A\n        B\n      C"),
+			Some((3, ParseErrorKind::InconsistentDedent { block: 2 }))
+		);
+		// Comments can't end blocks, so they don't have to line up
+		assert_eq!(error("K\n\t\tA\n\t# B"), None);
+		assert_eq!(error("K\n    A\n        B\n      # C\n        D"), None);
+	}
+
+	#[test]
+	fn mixed_indent() {
+		let text = "A\n\tB\n    C\n  \tD\n      E\n\t\tF f\nG\n";
+		let mixed = ParseOptions::new().mixed_indent(4);
+
+		let mut conf = Confindent::from_str_with(text, &mixed).unwrap();
+		assert_eq!(
+			conf.warnings(),
+			[
+				ParseError {
+					line: 2,
+					kind: ParseErrorKind::SpacesWithTabs
+				},
+				ParseError {
+					line: 3,
+					kind: ParseErrorKind::MixedIndent
+				},
+				ParseError {
+					line: 5,
+					kind: ParseErrorKind::TabsWithSpaces
+				},
+			]
+		);
+
+		// The indents are only changed by normalizing
+		assert_eq!(conf.get("A/D/E/F"), Some("f"));
+		assert_eq!(conf.to_string(), text);
+		conf.normalize();
+		assert_eq!(conf.to_string(), "A\n\tB\n\tC\n\tD\n\t\tE\n\t\t\tF f\nG\n");
+
+		let borrowed = ConfindentRef::parse_with(text, &mixed).unwrap();
+		assert_eq!(borrowed.into_owned().to_string(), text);
+
+		let commented = "A\n\tB\n  \t# c\n  \tC\n";
+		let mut conf = Confindent::from_str_with(commented, &mixed).unwrap();
+		assert_eq!(conf.to_string(), commented);
+		conf.normalize();
+		assert_eq!(conf.to_string(), "A\n\tB\n\t# c\n\tC\n");
+
+		let events = Parser::with_options(text.as_bytes(), &mixed).count();
+		assert_eq!(events, 14);
+
+		let strict = mixed.strict_dedent(true);
+		assert_eq!(
+			Confindent::from_str_with("A\n\t\tB\n    C", &strict),
+			Err(ParseError {
+				line: 2,
+				kind: ParseErrorKind::InconsistentDedent { block: 1 }
+			})
+		);
+	}
+}
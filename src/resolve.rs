@@ -0,0 +1,341 @@
+//! Replacing values that point somewhere else, like `env:HOME`, with what
+//! they point to.
+
+use core::fmt;
+use std::{collections::HashMap, env, error::Error as StdError, fs};
+
+use crate::{line::Line, Confindent, Secret};
+
+/// Finds the value a reference points to. See [Resolvers].
+///
+/// Closures taking the reference and returning the value, or a message
+/// saying why there isn't one, are resolvers too.
+pub trait Resolver {
+	/// Find the value for what was written after the scheme, like
+	/// `/run/secrets/db` in `file:/run/secrets/db`.
+	fn resolve(&self, reference: &str) -> Result<String, String>;
+}
+
+impl<F: Fn(&str) -> Result<String, String>> Resolver for F {
+	fn resolve(&self, reference: &str) -> Result<String, String> {
+		self(reference)
+	}
+}
+
+/// The [Resolver]s for each scheme, and what they've found so far.
+///
+/// A value starting with a scheme and a colon, like `env:DB_PASSWORD`, is
+/// replaced with what that scheme's resolver finds when the document is
+/// [resolved](Confindent::resolve). Values with a scheme that hasn't been
+/// registered, like `https://example.com`, are left alone.
+///
+/// Two are registered already:
+/// - `env:` is the environment variable with the name
+/// - `file:` is the file at the path, without its last line ending
+///
+/// Everything found is kept, so a reference that's used more than once,
+/// or in more than one document, is only looked up the first time. It's
+/// kept as a [Secret], so it's cleared from memory when it's forgotten.
+///
+/// # Example
+///
+/// ```rust
+/// use confindent::{Confindent, Resolvers};
+///
+/// let mut resolvers = Resolvers::new().register("upper", |reference: &str| {
+/// 	Ok(reference.to_uppercase())
+/// });
+///
+/// let mut conf: Confindent = "Name upper:gen\nUrl https://example.com".parse().unwrap();
+/// conf.resolve(&mut resolvers).unwrap();
+///
+/// assert_eq!(conf.get("Name"), Some("GEN"));
+/// assert_eq!(conf.get("Url"), Some("https://example.com"));
+/// ```
+///
+/// Running commands isn't built in, but it's easy to add if you trust where
+/// your config comes from:
+///
+/// ```rust,no_run
+/// use std::process::Command;
+/// use confindent::Resolvers;
+///
+/// let resolvers = Resolvers::new().register("cmd", |command: &str| {
+/// 	let output = Command::new("sh")
+/// 		.args(["-c", command])
+/// 		.output()
+/// 		.map_err(|e| e.to_string())?;
+/// 	let output = String::from_utf8(output.stdout).map_err(|e| e.to_string())?;
+/// 	Ok(output.trim_end().to_owned())
+/// });
+/// ```
+pub struct Resolvers {
+	schemes: Vec<(String, Box<dyn Resolver>)>,
+	/// What whole values, scheme and all, resolved to
+	cache: HashMap<String, Secret>,
+}
+
+/// A value that couldn't be resolved.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ResolveError {
+	/// The `/` separated path to the value
+	pub path: String,
+	/// The line the value is on, starting from zero
+	pub line: Option<usize>,
+	/// The scheme of the value, like `env`
+	pub scheme: String,
+	/// What the resolver said went wrong
+	pub message: String,
+}
+
+impl Resolvers {
+	/// The built in `env:` and `file:` resolvers.
+	pub fn new() -> Self {
+		Self::empty().register("env", env).register("file", file)
+	}
+
+	/// No resolvers at all, not even the built in ones.
+	pub fn empty() -> Self {
+		Self {
+			schemes: vec![],
+			cache: HashMap::new(),
+		}
+	}
+
+	/// Resolve values starting with `scheme:` with the resolver, instead of
+	/// whatever did before.
+	pub fn register<S, R>(mut self, scheme: S, resolver: R) -> Self
+	where
+		S: Into<String>,
+		R: Resolver + 'static,
+	{
+		let scheme = scheme.into();
+		self.schemes.retain(|(existing, _)| *existing != scheme);
+		self.cache
+			.retain(|value, _| !value.starts_with(&format!("{scheme}:")));
+		self.schemes.push((scheme, Box::new(resolver)));
+		self
+	}
+
+	/// Forget everything that's been found, so it's looked up again.
+	pub fn clear_cache(&mut self) {
+		self.cache.clear();
+	}
+
+	/// What the value resolves to, or None if it doesn't have a scheme we
+	/// know about.
+	fn resolve(&mut self, value: &str) -> Option<Result<String, (String, String)>> {
+		let (scheme, reference) = value.split_once(':')?;
+		let (_, resolver) = self.schemes.iter().find(|(known, _)| known == scheme)?;
+
+		if let Some(found) = self.cache.get(value) {
+			return Some(Ok(found.expose().to_owned()));
+		}
+
+		Some(match resolver.resolve(reference) {
+			Ok(found) => {
+				let found = Secret::new(found);
+				let resolved = found.expose().to_owned();
+				self.cache.insert(value.to_owned(), found);
+				Ok(resolved)
+			}
+			Err(message) => Err((scheme.to_owned(), message)),
+		})
+	}
+}
+
+impl Default for Resolvers {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+impl Confindent {
+	/// Replace every value that starts with a scheme in `resolvers` with
+	/// what it points to. See [Resolvers].
+	///
+	/// Resolved values are often passwords, so they're marked
+	/// [secret](crate::Value::is_secret) and printed as `<redacted>`. Getting them
+	/// still gets what was found.
+	///
+	/// If any value can't be resolved, nothing is changed and the error says
+	/// which value it was. The references are gone once they're resolved, so
+	/// if you'll be saving the document, save the one you didn't resolve.
+	pub fn resolve(&mut self, resolvers: &mut Resolvers) -> Result<(), ResolveError> {
+		let mut found = vec![];
+		find(&self.children, "", resolvers, &mut found)?;
+
+		let mut found = found.into_iter();
+		replace(&mut self.children, resolvers, &mut found);
+		Ok(())
+	}
+}
+
+/// Resolve every value in the lines, and their children, in order.
+fn find(
+	lines: &[Line],
+	parent: &str,
+	resolvers: &mut Resolvers,
+	found: &mut Vec<String>,
+) -> Result<(), ResolveError> {
+	for line in lines {
+		let Line::Value(value) = line else {
+			continue;
+		};
+
+		let path = match parent {
+			"" => value.key.clone(),
+			parent => format!("{parent}/{}", value.key),
+		};
+
+		match value
+			.value
+			.as_deref()
+			.and_then(|raw| resolvers.resolve(raw))
+		{
+			None => (),
+			Some(Ok(resolved)) => found.push(resolved),
+			Some(Err((scheme, message))) => {
+				return Err(ResolveError {
+					path,
+					line: value.line,
+					scheme,
+					message,
+				})
+			}
+		}
+
+		find(&value.children, &path, resolvers, found)?;
+	}
+
+	Ok(())
+}
+
+/// Put what [find] found where it came from. Whether a value has a scheme
+/// hasn't changed, so they line up.
+fn replace(lines: &mut [Line], resolvers: &Resolvers, found: &mut impl Iterator<Item = String>) {
+	for line in lines {
+		let Line::Value(value) = line else {
+			continue;
+		};
+
+		let has_scheme = value
+			.value
+			.as_deref()
+			.and_then(|raw| raw.split_once(':'))
+			.is_some_and(|(scheme, _)| resolvers.schemes.iter().any(|(known, _)| known == scheme));
+		if has_scheme {
+			value.value = found.next();
+			value.secret = true;
+		}
+
+		replace(&mut value.children, resolvers, found);
+	}
+}
+
+fn env(name: &str) -> Result<String, String> {
+	env::var(name).map_err(|e| match e {
+		env::VarError::NotPresent => format!("{name} isn't set"),
+		env::VarError::NotUnicode(_) => format!("{name} isn't valid unicode"),
+	})
+}
+
+fn file(path: &str) -> Result<String, String> {
+	let mut contents = fs::read_to_string(path).map_err(|e| format!("{path}: {e}"))?;
+
+	if contents.ends_with('\n') {
+		contents.pop();
+		if contents.ends_with('\r') {
+			contents.pop();
+		}
+	}
+
+	Ok(contents)
+}
+
+impl StdError for ResolveError {}
+impl fmt::Display for ResolveError {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		let ResolveError {
+			path,
+			line,
+			scheme,
+			message,
+		} = self;
+
+		match line {
+			None => write!(f, "{path}: couldn't resolve {scheme}: {message}"),
+			Some(line) => write!(
+				f,
+				"{path} on line {line}: couldn't resolve {scheme}: {message}"
+			),
+		}
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use std::{cell::Cell, rc::Rc};
+
+	use super::*;
+
+	#[test]
+	fn resolves_and_caches() {
+		let path = env::temp_dir().join(format!("confindent-resolve-{}", std::process::id()));
+		fs::write(&path, "hunter2\r\n").unwrap();
+
+		let calls = Rc::new(Cell::new(0));
+		let counted = Rc::clone(&calls);
+		let mut resolvers = Resolvers::new().register("count", move |reference: &str| {
+			counted.set(counted.get() + 1);
+			Ok(format!("counted {reference}"))
+		});
+
+		let text = format!(
+			"Db\n\tPassword file:{}\n\tUser count:a\nAgain count:a\nHome http://x",
+			path.display()
+		);
+		let mut conf: Confindent = text.parse().unwrap();
+		conf.resolve(&mut resolvers).unwrap();
+		fs::remove_file(&path).unwrap();
+
+		assert_eq!(conf.get("Db/Password"), Some("hunter2"));
+		assert_eq!(conf.get("Db/User"), Some("counted a"));
+		assert_eq!(conf.get("Again"), Some("counted a"));
+		assert_eq!(conf.get("Home"), Some("http://x"));
+		assert_eq!(calls.get(), 1);
+
+		assert_eq!(
+			conf.to_string(),
+			"Db\n\tPassword <redacted>\n\tUser <redacted>\nAgain <redacted>\nHome http://x"
+		);
+	}
+
+	#[test]
+	fn errors_say_where() {
+		let text = "Db\n\tUser env:USER_THAT_IS_NOT_SET_ANYWHERE\n\tPassword bad:x";
+		let mut conf: Confindent = text.parse().unwrap();
+		let mut resolvers = Resolvers::new().register("bad", |_: &str| Err("nope".into()));
+
+		let err = conf.resolve(&mut resolvers).unwrap_err();
+		assert_eq!(
+			err,
+			ResolveError {
+				path: "Db/User".into(),
+				line: Some(1),
+				scheme: "env".into(),
+				message: "USER_THAT_IS_NOT_SET_ANYWHERE isn't set".into()
+			}
+		);
+
+		let mut resolvers = Resolvers::empty().register("bad", |_: &str| Err("nope".into()));
+		let err = conf.resolve(&mut resolvers).unwrap_err();
+		assert_eq!(
+			err.to_string(),
+			"Db/Password on line 2: couldn't resolve bad: nope"
+		);
+
+		// Nothing changes unless everything resolves
+		assert_eq!(conf.to_string(), text);
+	}
+}
@@ -0,0 +1,223 @@
+//! Saving documents without leaving a half written file behind.
+
+use std::{
+	ffi::OsString,
+	fs::{self, File, OpenOptions},
+	io::{self, Write},
+	path::{Path, PathBuf},
+};
+
+use crate::Confindent;
+
+/// How a document is saved. See [Confindent::save_with].
+///
+/// However it's saved, the document is written to a new file next to the
+/// old one, which then replaces it. Anyone reading the file sees either
+/// all of the old document or all of the new one, even if we crash part of
+/// the way through.
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use confindent::{Confindent, SaveOptions};
+///
+/// let mut conf = Confindent::from_file("daemon.conf").unwrap();
+/// conf.set("Server/Port", Some(2222));
+///
+/// let options = SaveOptions::new().backup(true).lock(true);
+/// conf.save_with("daemon.conf", &options).unwrap();
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct SaveOptions {
+	backup: bool,
+	lock: bool,
+}
+
+impl SaveOptions {
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	/// Copy the file to the same path with `.bak` on the end before
+	/// replacing it, if there's a file to copy. An older backup is
+	/// overwritten.
+	pub fn backup(mut self, backup: bool) -> Self {
+		self.backup = backup;
+		self
+	}
+
+	/// Hold a lock on the same path with `.lock` on the end while saving,
+	/// so programs saving the same file with this take turns.
+	///
+	/// The lock is advisory, so it only keeps out others who ask for it too.
+	/// The lock file is left behind, because removing it would let someone
+	/// lock a new one while someone else is still waiting on the old one.
+	pub fn lock(mut self, lock: bool) -> Self {
+		self.lock = lock;
+		self
+	}
+}
+
+impl Confindent {
+	/// Save the document to `path` the way the options say to.
+	///
+	/// The file is replaced, not written over, so if `path` is a symlink
+	/// the file it points to is what's replaced. The new file has the same
+	/// permissions as the old one.
+	pub fn save_with<P: AsRef<Path>>(
+		&self,
+		path: P,
+		options: &SaveOptions,
+	) -> Result<(), io::Error> {
+		let path = target(path.as_ref());
+		let _lock = match options.lock {
+			false => None,
+			true => Some(lock(&path)?),
+		};
+
+		let (temp, file) = temp_file(&path)?;
+		let saved = self.replace(file, &temp, &path, options);
+		if saved.is_err() {
+			let _ = fs::remove_file(&temp);
+		}
+		saved
+	}
+
+	/// Write ourselves to `file`, at `temp`, and put it where `path` is.
+	fn replace(
+		&self,
+		mut file: File,
+		temp: &Path,
+		path: &Path,
+		options: &SaveOptions,
+	) -> Result<(), io::Error> {
+		file.write_all(self.unredacted().to_string().as_bytes())?;
+
+		let existing = fs::metadata(path).ok();
+		if let Some(existing) = &existing {
+			file.set_permissions(existing.permissions())?;
+		}
+		file.sync_all()?;
+		drop(file);
+
+		if options.backup && existing.is_some() {
+			fs::copy(path, with_suffix(path, ".bak"))?;
+		}
+
+		fs::rename(temp, path)?;
+		sync_dir(path);
+		Ok(())
+	}
+}
+
+/// The file that should be replaced, which is what `path` points to if
+/// it's a symlink.
+fn target(path: &Path) -> PathBuf {
+	match fs::symlink_metadata(path) {
+		Ok(metadata) if metadata.file_type().is_symlink() => {
+			fs::canonicalize(path).unwrap_or_else(|_| path.to_owned())
+		}
+		_ => path.to_owned(),
+	}
+}
+
+fn lock(path: &Path) -> Result<File, io::Error> {
+	let file = OpenOptions::new()
+		.create(true)
+		.truncate(false)
+		.write(true)
+		.open(with_suffix(path, ".lock"))?;
+	file.lock()?;
+	Ok(file)
+}
+
+/// Make a new, hidden file in the same directory as `path`. It has to be in
+/// the same directory so it can be renamed over it.
+fn temp_file(path: &Path) -> Result<(PathBuf, File), io::Error> {
+	let name = path
+		.file_name()
+		.ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "can't save to a directory"))?;
+	let dir = path.parent().unwrap_or(Path::new(""));
+
+	let mut attempt = 0;
+	loop {
+		let mut temp = OsString::from(".");
+		temp.push(name);
+		temp.push(format!(".{}.{attempt}.tmp", std::process::id()));
+		let temp = dir.join(temp);
+
+		match OpenOptions::new().write(true).create_new(true).open(&temp) {
+			Ok(file) => return Ok((temp, file)),
+			Err(e) if e.kind() == io::ErrorKind::AlreadyExists && attempt < 100 => attempt += 1,
+			Err(e) => return Err(e),
+		}
+	}
+}
+
+fn with_suffix(path: &Path, suffix: &str) -> PathBuf {
+	let mut path = path.as_os_str().to_owned();
+	path.push(suffix);
+	path.into()
+}
+
+/// Make sure the rename itself is on disk. This is only needed, and only
+/// possible, on unix. If it fails the file is still saved, it's just not
+/// certain to survive a crash, so there's no error.
+fn sync_dir(path: &Path) {
+	#[cfg(unix)]
+	{
+		let dir = match path.parent() {
+			Some(dir) if dir != Path::new("") => dir,
+			_ => Path::new("."),
+		};
+		let _ = File::open(dir).and_then(|dir| dir.sync_all());
+	}
+	#[cfg(not(unix))]
+	let _ = path;
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	#[test]
+	fn replaces_the_file() {
+		let dir = std::env::temp_dir().join(format!("confindent-save-{}", std::process::id()));
+		fs::create_dir_all(&dir).unwrap();
+		let path = dir.join("test.conf");
+		fs::write(&path, "Old file").unwrap();
+
+		#[cfg(unix)]
+		{
+			use std::os::unix::fs::PermissionsExt;
+			fs::set_permissions(&path, fs::Permissions::from_mode(0o640)).unwrap();
+		}
+
+		let conf: Confindent = "New file\n\tChild".parse().unwrap();
+		let options = SaveOptions::new().backup(true).lock(true);
+		conf.save_with(&path, &options).unwrap();
+
+		assert_eq!(fs::read_to_string(&path).unwrap(), "New file\n\tChild");
+		assert_eq!(
+			fs::read_to_string(dir.join("test.conf.bak")).unwrap(),
+			"Old file"
+		);
+
+		#[cfg(unix)]
+		{
+			use std::os::unix::fs::PermissionsExt;
+			let mode = fs::metadata(&path).unwrap().permissions().mode();
+			assert_eq!(mode & 0o777, 0o640);
+		}
+
+		// Only what we meant to leave behind is there
+		let mut names: Vec<_> = fs::read_dir(&dir)
+			.unwrap()
+			.map(|entry| entry.unwrap().file_name())
+			.collect();
+		names.sort();
+		assert_eq!(names, ["test.conf", "test.conf.bak", "test.conf.lock"]);
+
+		fs::remove_dir_all(&dir).unwrap();
+	}
+}
@@ -0,0 +1,749 @@
+//! Describe what a configuration should look like and check documents
+//! against it.
+//!
+//! A [Schema] is a set of [Rule]s, each for a `/` separated path of keys
+//! like the ones [Confindent::get] takes. A rule for `Host/Port` applies to
+//! the `Port` of every `Host`.
+//!
+//! ```rust
+//! use confindent::{
+//! 	schema::{Kind, Rule, Schema, ViolationKind},
+//! 	Confindent,
+//! };
+//!
+//! let schema = Schema::new()
+//! 	.rule("Host", Rule::new().required().allow_children(["Port", "User"]))
+//! 	.rule("Host/Port", Rule::new().kind(Kind::Int).min(1.0).max(65535.0))
+//! 	.rule("Host/User", Rule::new().at_most(1));
+//!
+//! let conf: Confindent = "Host example.com\n\tPort 80000\n\tProxy none".parse().unwrap();
+//! let violations = schema.validate(&conf);
+//!
+//! assert_eq!(violations.len(), 2);
+//! assert_eq!(violations[0].path, "Host/Port");
+//! assert_eq!(violations[0].line, Some(1));
+//! assert!(matches!(violations[0].kind, ViolationKind::OutOfRange { .. }));
+//! assert_eq!(violations[1].path, "Host/Proxy");
+//! assert_eq!(violations[1].kind, ViolationKind::UnknownKey);
+//! ```
+//!
+//! # Schema files
+//!
+//! A schema can also be written in confindent. Every `Key` describes a key
+//! at that level, and the lines under it say what it has to look like:
+//!
+//! - `Type` is one of `string`, `int`, `float`, `bool`, or a sized integer
+//!   like `u16` or `i32`, which also limits the value to what fits
+//! - `OneOf` lists the words the value can be, `Pattern` is a [Pattern]
+//! - `Min` and `Max` limit how big a number the value can be
+//! - `Required`, `AtLeast`, and `AtMost` are how many times the key can be
+//!   there
+//! - `NoChildren` forbids children, and `Strict` only allows the children
+//!   that have a `Key`
+//! - `Secret` keeps the value out of anything printed, once the schema
+//!   [marks](Schema::mark_secrets) it
+//! - `Key` describes a child
+//! - `Use` builds on a `Define`, which has the same lines as a `Key`
+//!
+//! `Strict` can also be used without any indentation for keys at the top.
+//! A definition can `Use` itself to describe blocks nested in themselves.
+//!
+//! ```rust
+//! use confindent::{schema::Schema, Confindent};
+//!
+//! let schema: Schema = "Key Host\n\tRequired\n\tUse Host\n\
+//! 	Define Host\n\tStrict\n\tKey Port\n\t\tType u16\n\tKey Host\n\t\tUse Host"
+//! 	.parse()
+//! 	.unwrap();
+//!
+//! let conf: Confindent = "Host a\n\tPort 22\n\tHost b\n\t\tPort 99999".parse().unwrap();
+//! let violations = schema.validate(&conf);
+//!
+//! assert_eq!(violations.len(), 1);
+//! assert_eq!(violations[0].path, "Host/Host/Port");
+//! assert_eq!(violations[0].line, Some(3));
+//! ```
+
+mod load;
+mod pattern;
+
+use core::fmt;
+use std::{collections::BTreeMap, error::Error as StdError, ptr};
+
+pub use load::{SchemaError, SchemaErrorKind};
+pub use pattern::{Pattern, PatternError};
+
+use crate::{secret::REDACTED, typed, Confindent, KeyCase, Value};
+
+/// A set of rules for what a document should look like.
+///
+/// Schemas can be built up in code, or written as a confindent document of
+/// their own and loaded with [from_str](std::str::FromStr::from_str) or
+/// [Schema::from_file]. See [the module docs](self) for what those look
+/// like.
+#[derive(Clone, Debug, Default)]
+pub struct Schema {
+	/// The rules for keys without any indentation are the children of this
+	root: Rule,
+	definitions: BTreeMap<String, Rule>,
+}
+
+/// What a key, at a path, has to look like.
+///
+/// By default a key can appear any number of times, have any value or none
+/// at all, and have any children.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Rule {
+	min: Option<usize>,
+	max: Option<usize>,
+	kind: Option<Kind>,
+	low: Option<f64>,
+	high: Option<f64>,
+	children: Option<bool>,
+	allowed: Option<Vec<String>>,
+	strict: Option<bool>,
+	rules: BTreeMap<String, Rule>,
+	/// The name of the definition this rule builds on
+	like: Option<String>,
+	secret: Option<bool>,
+}
+
+/// What kind of value a key has to have.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Kind {
+	/// Any value at all, so long as there is one
+	String,
+	/// A whole number that fits in an `i64`
+	Int,
+	/// A whole number that fits in a `u64`
+	Unsigned,
+	/// Any number that fits in an `f64`
+	Float,
+	/// A bool written like `yes`, `off`, or `true`.
+	/// See [Value::parse_bool](crate::Value::parse_bool)
+	Bool,
+	/// One of a list of values
+	OneOf(Vec<String>),
+	/// A value matching a [Pattern]
+	Matches(Pattern),
+}
+
+/// Something in a document that doesn't follow the [Schema].
+#[derive(Clone, Debug, PartialEq)]
+pub struct Violation {
+	/// The `/` separated path to the key
+	pub path: String,
+	/// The line of the offending value, or of the parent of a missing one.
+	/// Lines start from zero like they do in [ParseError](crate::ParseError).
+	pub line: Option<usize>,
+	pub kind: ViolationKind,
+}
+
+/// What was wrong, exactly.
+#[derive(Clone, Debug, PartialEq)]
+pub enum ViolationKind {
+	/// A required key isn't there
+	Missing,
+	/// A key isn't there as many times as it has to be
+	TooFew { min: usize, found: usize },
+	/// A key is there more times than it's allowed to be
+	TooMany { max: usize, found: usize },
+	/// The key has to have a value and doesn't
+	MissingValue,
+	/// The value isn't the right kind
+	InvalidValue { expected: Kind, value: String },
+	/// The value is a number but it's too big or too small. It's as it was
+	/// written, or `<redacted>` if it's secret.
+	OutOfRange {
+		value: String,
+		min: Option<f64>,
+		max: Option<f64>,
+	},
+	/// The key isn't allowed to have children but it does
+	UnexpectedChildren,
+	/// The key isn't one of those allowed here
+	UnknownKey,
+}
+
+impl Schema {
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	/// Add a rule for the key at the path, replacing any rule that was
+	/// already there. Rules already added for the key's children are kept.
+	pub fn rule<S: AsRef<str>>(mut self, path: S, rule: Rule) -> Self {
+		let mut slot = &mut self.root;
+		for key in path.as_ref().split('/') {
+			slot = slot.rules.entry(key.to_owned()).or_default();
+		}
+
+		let nested = std::mem::replace(slot, rule).rules;
+		for (key, rule) in nested {
+			slot.rules.entry(key).or_insert(rule);
+		}
+
+		self
+	}
+
+	/// Add a rule that other rules can build on with [Rule::like].
+	pub fn define<S: Into<String>>(mut self, name: S, rule: Rule) -> Self {
+		self.definitions.insert(name.into(), rule);
+		self
+	}
+
+	/// Only allow these keys without any indentation.
+	pub fn allow_keys<I, S>(mut self, keys: I) -> Self
+	where
+		I: IntoIterator<Item = S>,
+		S: Into<String>,
+	{
+		self.root.allowed = Some(keys.into_iter().map(Into::into).collect());
+		self
+	}
+
+	/// Only allow the keys there are rules for without any indentation.
+	pub fn strict(mut self) -> Self {
+		self.root.strict = Some(true);
+		self
+	}
+
+	/// Get the rule for a path, if there is one.
+	pub fn get<S: AsRef<str>>(&self, path: S) -> Option<&Rule> {
+		let mut current = &self.root;
+		for key in path.as_ref().split('/') {
+			current = current.rules.get(key)?;
+		}
+
+		Some(current)
+	}
+
+	/// Get a definition by its name.
+	pub fn definition<S: AsRef<str>>(&self, name: S) -> Option<&Rule> {
+		self.definitions.get(name.as_ref())
+	}
+
+	/// Check the document against every rule and return everything wrong
+	/// with it. If nothing is wrong, the document is valid.
+	pub fn validate(&self, conf: &Confindent) -> Vec<Violation> {
+		let mut violations = vec![];
+		let values: Vec<&Value> = conf.values().collect();
+
+		let root = self.resolve(&self.root);
+		self.check_level("", None, &values, &root, conf.key_case(), &mut violations);
+		violations
+	}
+
+	/// Mark every value with a [secret](Rule::secret) rule as secret, so
+	/// it's printed as `<redacted>`. See [Confindent::mark_secret].
+	///
+	/// # Example
+	///
+	/// ```rust
+	/// use confindent::{schema::Schema, Confindent};
+	///
+	/// let schema: Schema = "Key Database\n\tKey Password\n\t\tSecret".parse().unwrap();
+	/// let mut conf: Confindent = "Database\n\tPassword hunter2".parse().unwrap();
+	/// schema.mark_secrets(&mut conf);
+	///
+	/// assert_eq!(conf.to_string(), "Database\n\tPassword <redacted>");
+	/// ```
+	pub fn mark_secrets(&self, conf: &mut Confindent) {
+		let root = self.resolve(&self.root);
+		let case = conf.key_case();
+		self.mark_level(conf.values_mut(), &root, case);
+	}
+
+	fn mark_level<'v, I>(&self, values: I, rules: &Resolved, case: KeyCase)
+	where
+		I: Iterator<Item = &'v mut Value>,
+	{
+		for value in values {
+			let Some(rule) = rules.child(&value.key, case) else {
+				continue;
+			};
+			let rule = self.resolve(rule);
+
+			if rule.find(|r| r.secret) == Some(true) {
+				value.secret = true;
+			}
+			let case = value.key_case();
+			self.mark_level(value.values_mut(), &rule, case);
+		}
+	}
+
+	/// The rule followed by every definition it builds on, so that things
+	/// the rule leaves unsaid can come from them.
+	fn resolve<'s>(&'s self, rule: &'s Rule) -> Resolved<'s> {
+		let mut chain = vec![rule];
+		let mut current = rule;
+
+		while let Some(name) = &current.like {
+			match self.definitions.get(name) {
+				// A definition can contain itself, but can't build on itself
+				Some(def) if !chain.iter().any(|seen| ptr::eq(*seen, def)) => {
+					chain.push(def);
+					current = def;
+				}
+				_ => break,
+			}
+		}
+
+		Resolved { chain }
+	}
+
+	/// Check a set of siblings, and then their children.
+	fn check_level(
+		&self,
+		parent: &str,
+		parent_line: Option<usize>,
+		values: &[&Value],
+		rules: &Resolved,
+		case: KeyCase,
+		violations: &mut Vec<Violation>,
+	) {
+		let path_of = |key: &str| {
+			if parent.is_empty() {
+				key.to_owned()
+			} else {
+				format!("{parent}/{key}")
+			}
+		};
+
+		for (key, rule) in rules.children() {
+			let rule = self.resolve(rule);
+			let min = rule.find(|r| r.min).unwrap_or(0);
+			let max = rule.find(|r| r.max);
+
+			let found = values
+				.iter()
+				.filter(|value| case.matches(&value.key, key))
+				.count();
+			let kind = if found < min {
+				if found == 0 {
+					ViolationKind::Missing
+				} else {
+					ViolationKind::TooFew { min, found }
+				}
+			} else {
+				match max {
+					Some(max) if found > max => ViolationKind::TooMany { max, found },
+					_ => continue,
+				}
+			};
+
+			violations.push(Violation {
+				path: path_of(key),
+				line: parent_line,
+				kind,
+			});
+		}
+
+		let allowed = rules.find(|r| r.allowed.as_deref());
+		let strict = rules.find(|r| r.strict).unwrap_or(false);
+
+		for value in values {
+			let path = path_of(&value.key);
+			let violation = |kind| Violation {
+				path: path.clone(),
+				line: value.line,
+				kind,
+			};
+
+			let rule = rules.child(&value.key, case);
+			let unlisted = allowed
+				.map(|allowed| !allowed.iter().any(|key| case.matches(key, &value.key)))
+				.unwrap_or(false);
+			if unlisted || (strict && rule.is_none()) {
+				violations.push(violation(ViolationKind::UnknownKey));
+			}
+
+			// Nothing deeper can have a rule if this doesn't
+			let Some(rule) = rule else {
+				continue;
+			};
+			let rule = self.resolve(rule);
+
+			if let Err(kind) = rule.check_value(value) {
+				violations.push(violation(kind));
+			}
+
+			let children: Vec<&Value> = value.values().collect();
+			if rule.find(|r| r.children) == Some(false) && !children.is_empty() {
+				violations.push(violation(ViolationKind::UnexpectedChildren));
+			}
+
+			self.check_level(
+				&path,
+				value.line,
+				&children,
+				&rule,
+				value.key_case(),
+				violations,
+			);
+		}
+	}
+}
+
+impl Rule {
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	/// The key has to be there at least once.
+	pub fn required(self) -> Self {
+		self.at_least(1)
+	}
+
+	/// The key has to be there at least this many times.
+	pub fn at_least(mut self, min: usize) -> Self {
+		self.min = Some(min);
+		self
+	}
+
+	/// The key can't be there more than this many times.
+	pub fn at_most(mut self, max: usize) -> Self {
+		self.max = Some(max);
+		self
+	}
+
+	/// The key has to have a value, and it has to be this kind.
+	pub fn kind(mut self, kind: Kind) -> Self {
+		self.kind = Some(kind);
+		self
+	}
+
+	/// The value has to be a number at least this big.
+	pub fn min(mut self, min: f64) -> Self {
+		self.low = Some(min);
+		self
+	}
+
+	/// The value has to be a number no bigger than this.
+	pub fn max(mut self, max: f64) -> Self {
+		self.high = Some(max);
+		self
+	}
+
+	/// The key can't have any children.
+	pub fn no_children(mut self) -> Self {
+		self.children = Some(false);
+		self
+	}
+
+	/// The key can have children, but only with these keys.
+	pub fn allow_children<I, S>(mut self, keys: I) -> Self
+	where
+		I: IntoIterator<Item = S>,
+		S: Into<String>,
+	{
+		self.children = Some(true);
+		self.allowed = Some(keys.into_iter().map(Into::into).collect());
+		self
+	}
+
+	/// Only allow children that there are rules for.
+	pub fn strict(mut self) -> Self {
+		self.strict = Some(true);
+		self
+	}
+
+	/// Add a rule for a child of this key.
+	pub fn child<S: Into<String>>(mut self, key: S, rule: Rule) -> Self {
+		self.rules.insert(key.into(), rule);
+		self
+	}
+
+	/// The value is secret, and [Schema::mark_secrets] marks it so.
+	pub fn secret(mut self) -> Self {
+		self.secret = Some(true);
+		self
+	}
+
+	/// Build on a definition from [Schema::define]. Anything this rule
+	/// doesn't say comes from the definition, including the rules for
+	/// children, so a definition can describe blocks nested in themselves.
+	pub fn like<S: Into<String>>(mut self, name: S) -> Self {
+		self.like = Some(name.into());
+		self
+	}
+}
+
+/// A rule and the definitions it builds on, first to last.
+struct Resolved<'s> {
+	chain: Vec<&'s Rule>,
+}
+
+impl<'s> Resolved<'s> {
+	/// The first thing any of the rules says.
+	fn find<T, F: Fn(&'s Rule) -> Option<T>>(&self, f: F) -> Option<T> {
+		self.chain.iter().find_map(|rule| f(rule))
+	}
+
+	fn child(&self, key: &str, case: KeyCase) -> Option<&'s Rule> {
+		self.find(|rule| match case {
+			KeyCase::Sensitive => rule.rules.get(key),
+			case => rule
+				.rules
+				.iter()
+				.find_map(|(rule_key, rule)| case.matches(rule_key, key).then_some(rule)),
+		})
+	}
+
+	/// Every key with a rule, in order, each once.
+	fn children(&self) -> Vec<(&'s str, &'s Rule)> {
+		let mut children: Vec<(&str, &Rule)> = vec![];
+		for rule in &self.chain {
+			for (key, rule) in &rule.rules {
+				if !children.iter().any(|(seen, _)| seen == key) {
+					children.push((key, rule));
+				}
+			}
+		}
+
+		children
+	}
+
+	fn check_value(&self, value: &Value) -> Result<(), ViolationKind> {
+		let kind = self.find(|r| r.kind.as_ref());
+		let low = self.find(|r| r.low);
+		let high = self.find(|r| r.high);
+
+		let ranged = low.is_some() || high.is_some();
+		if kind.is_none() && !ranged {
+			return Ok(());
+		}
+
+		let raw = value.value().ok_or(ViolationKind::MissingValue)?;
+		let shown = if value.is_secret() { REDACTED } else { raw };
+		let invalid = |expected: Kind| ViolationKind::InvalidValue {
+			expected,
+			value: shown.to_owned(),
+		};
+
+		if let Some(kind) = kind {
+			if !kind.accepts(raw) {
+				return Err(invalid(kind.clone()));
+			}
+		}
+
+		if ranged {
+			// NaN is neither bigger nor smaller than anything, so it would
+			// pass any range
+			let number: f64 = raw
+				.parse()
+				.ok()
+				.filter(|number: &f64| number.is_finite())
+				.ok_or_else(|| invalid(Kind::Float))?;
+
+			let too_small = low.map(|low| number < low).unwrap_or(false);
+			let too_big = high.map(|high| number > high).unwrap_or(false);
+			if too_small || too_big {
+				return Err(ViolationKind::OutOfRange {
+					value: shown.to_owned(),
+					min: low,
+					max: high,
+				});
+			}
+		}
+
+		Ok(())
+	}
+}
+
+impl Kind {
+	/// Whether the value is this kind.
+	pub fn accepts(&self, value: &str) -> bool {
+		match self {
+			Kind::String => true,
+			Kind::Int => value.parse::<i64>().is_ok(),
+			Kind::Unsigned => value.parse::<u64>().is_ok(),
+			Kind::Float => value.parse::<f64>().is_ok(),
+			Kind::Bool => typed::parse_bool(value).is_ok(),
+			Kind::OneOf(options) => options.iter().any(|option| option == value),
+			Kind::Matches(pattern) => pattern.matches(value),
+		}
+	}
+}
+
+impl fmt::Display for Kind {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			Kind::String => write!(f, "a value"),
+			Kind::Int => write!(f, "a whole number"),
+			Kind::Unsigned => write!(f, "a whole number that isn't negative"),
+			Kind::Float => write!(f, "a number"),
+			Kind::Bool => write!(f, "yes or no"),
+			Kind::OneOf(options) => write!(f, "one of {}", options.join(", ")),
+			Kind::Matches(pattern) => write!(f, "a value matching `{pattern}`"),
+		}
+	}
+}
+
+impl StdError for Violation {}
+impl fmt::Display for Violation {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		let Violation { path, line, kind } = self;
+
+		match line {
+			None => write!(f, "{path}: {kind}"),
+			Some(line) => write!(f, "{path} on line {line}: {kind}"),
+		}
+	}
+}
+
+impl fmt::Display for ViolationKind {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			ViolationKind::Missing => write!(f, "required but missing"),
+			ViolationKind::TooFew { min, found } => {
+				write!(f, "needs to be here {min} times but was found {found}")
+			}
+			ViolationKind::TooMany { max, found } => {
+				write!(f, "can be here {max} times but was found {found}")
+			}
+			ViolationKind::MissingValue => write!(f, "needs a value"),
+			ViolationKind::InvalidValue { expected, value } => {
+				write!(f, "expected {expected} but found '{value}'")
+			}
+			ViolationKind::OutOfRange { value, min, max } => match (min, max) {
+				(Some(min), Some(max)) => write!(f, "{value} is not between {min} and {max}"),
+				(Some(min), None) => write!(f, "{value} is less than {min}"),
+				(None, Some(max)) => write!(f, "{value} is more than {max}"),
+				(None, None) => unreachable!(),
+			},
+			ViolationKind::UnexpectedChildren => write!(f, "can't have children"),
+			ViolationKind::UnknownKey => write!(f, "isn't allowed here"),
+		}
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	fn violations(schema: &Schema, conf: &str) -> Vec<(String, Option<usize>, ViolationKind)> {
+		let conf: Confindent = conf.parse().unwrap();
+		schema
+			.validate(&conf)
+			.into_iter()
+			.map(|v| (v.path, v.line, v.kind))
+			.collect()
+	}
+
+	#[test]
+	fn cardinality() {
+		let schema = Schema::new()
+			.rule("Name", Rule::new().required())
+			.rule("Server/Address", Rule::new().at_least(2).at_most(3));
+
+		assert_eq!(
+			violations(
+				&schema,
+				"Server one\n\tAddress a\nServer two\n\tAddress a\n\tAddress b"
+			),
+			vec![
+				("Name".into(), None, ViolationKind::Missing),
+				(
+					"Server/Address".into(),
+					Some(0),
+					ViolationKind::TooFew { min: 2, found: 1 }
+				),
+			]
+		);
+	}
+
+	#[test]
+	fn value_kinds() {
+		let schema = Schema::new()
+			.rule("Port", Rule::new().kind(Kind::Int))
+			.rule("Ratio", Rule::new().kind(Kind::Float).min(0.0).max(1.0))
+			.rule("Debug", Rule::new().kind(Kind::Bool))
+			.rule(
+				"Level",
+				Rule::new().kind(Kind::OneOf(vec!["low".into(), "high".into()])),
+			)
+			.rule(
+				"Name",
+				Rule::new().kind(Kind::Matches(Pattern::new("[a-z]+").unwrap())),
+			);
+
+		assert!(violations(
+			&schema,
+			"Port 22\nRatio 0.5\nDebug true\nLevel low\nName gen"
+		)
+		.is_empty());
+		assert_eq!(
+			violations(&schema, "Port x\nRatio 2\nDebug\nLevel mid\nName Gen"),
+			vec![
+				(
+					"Port".into(),
+					Some(0),
+					ViolationKind::InvalidValue {
+						expected: Kind::Int,
+						value: "x".into()
+					}
+				),
+				(
+					"Ratio".into(),
+					Some(1),
+					ViolationKind::OutOfRange {
+						value: "2".into(),
+						min: Some(0.0),
+						max: Some(1.0)
+					}
+				),
+				("Debug".into(), Some(2), ViolationKind::MissingValue),
+				(
+					"Level".into(),
+					Some(3),
+					ViolationKind::InvalidValue {
+						expected: Kind::OneOf(vec!["low".into(), "high".into()]),
+						value: "mid".into()
+					}
+				),
+				(
+					"Name".into(),
+					Some(4),
+					ViolationKind::InvalidValue {
+						expected: Kind::Matches(Pattern::new("[a-z]+").unwrap()),
+						value: "Gen".into()
+					}
+				),
+			]
+		);
+
+		// Numbers that aren't finite aren't in any range
+		let ranged = Schema::new().rule("Port", Rule::new().min(0.0).max(65535.0));
+		for port in ["NaN", "inf", "-infinity"] {
+			assert_eq!(
+				violations(&ranged, &format!("Port {port}")),
+				vec![(
+					"Port".into(),
+					Some(0),
+					ViolationKind::InvalidValue {
+						expected: Kind::Float,
+						value: port.into()
+					}
+				)]
+			);
+		}
+	}
+
+	#[test]
+	fn children() {
+		let schema = Schema::new()
+			.allow_keys(["Host", "Leaf"])
+			.rule("Leaf", Rule::new().no_children())
+			.rule("Host", Rule::new().allow_children(["Port"]));
+
+		assert_eq!(
+			violations(&schema, "Leaf\n\tChild\nHost\n\tPort 1\n\tUser gen\nOther"),
+			vec![
+				("Leaf".into(), Some(0), ViolationKind::UnexpectedChildren),
+				("Host/User".into(), Some(4), ViolationKind::UnknownKey),
+				("Other".into(), Some(5), ViolationKind::UnknownKey),
+			]
+		);
+	}
+}
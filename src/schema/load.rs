@@ -0,0 +1,444 @@
+use core::fmt;
+use std::{error::Error as StdError, fs, path::Path, str::FromStr};
+
+use super::{Kind, Pattern, PatternError, Rule, Schema};
+use crate::{Confindent, ParseError, ParseErrorKind, Value};
+
+/// The error returned when a schema document can't be loaded.
+#[derive(Debug, PartialEq)]
+pub struct SchemaError {
+	/// The line of the schema document the problem is on, starting from zero
+	pub line: usize,
+	pub kind: SchemaErrorKind,
+}
+
+/// What was wrong with the schema document.
+#[derive(Debug, PartialEq)]
+pub enum SchemaErrorKind {
+	/// The schema isn't a valid confindent document
+	Parse(ParseErrorKind),
+	/// A key that doesn't mean anything to a schema, at least not there
+	UnknownDirective(String),
+	/// The directive needs a value and doesn't have one
+	MissingValue(String),
+	/// The directive doesn't take a value but it has one
+	UnexpectedValue(String),
+	/// The directive was given more than once, or conflicts with another
+	Duplicate(String),
+	/// The value should have been a number
+	InvalidNumber(String),
+	/// A `Type` that isn't one we know
+	UnknownType(String),
+	/// A `Pattern` that couldn't be understood
+	InvalidPattern(PatternError),
+	/// A `Use` of a definition that doesn't exist
+	Undefined(String),
+}
+
+impl Schema {
+	/// Read and load a schema document from a file.
+	pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self, SchemaError> {
+		let string = fs::read_to_string(path).map_err(|_| SchemaError {
+			line: 0,
+			kind: SchemaErrorKind::Parse(ParseErrorKind::FileReadError),
+		})?;
+
+		string.parse()
+	}
+
+	/// Load a schema from an already parsed document.
+	pub fn from_confindent(conf: &Confindent) -> Result<Self, SchemaError> {
+		let mut loader = Loader { uses: vec![] };
+		let mut schema = Schema::new();
+
+		for value in conf.values() {
+			match value.key.as_str() {
+				"Key" => {
+					let key = required_value(value)?;
+					let rule = loader.rule(value)?;
+					insert(&mut schema.root, key, rule, value)?;
+				}
+				"Define" => {
+					let name = required_value(value)?;
+					let rule = loader.rule(value)?;
+					if schema.definitions.insert(name.to_owned(), rule).is_some() {
+						return Err(error(value, SchemaErrorKind::Duplicate(name.to_owned())));
+					}
+				}
+				"Strict" => {
+					no_value(value)?;
+					schema.root.strict = Some(true);
+				}
+				_ => return Err(error(value, unknown(value))),
+			}
+		}
+
+		// Definitions can be used before they're defined, so this waits
+		for (name, line) in loader.uses {
+			if !schema.definitions.contains_key(&name) {
+				return Err(SchemaError {
+					line,
+					kind: SchemaErrorKind::Undefined(name),
+				});
+			}
+		}
+
+		Ok(schema)
+	}
+}
+
+impl FromStr for Schema {
+	type Err = SchemaError;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		let conf: Confindent = s.parse()?;
+		Self::from_confindent(&conf)
+	}
+}
+
+struct Loader {
+	/// Every `Use`, and its line, to check once everything is defined
+	uses: Vec<(String, usize)>,
+}
+
+impl Loader {
+	/// Build a rule from the directives under a `Key` or `Define`.
+	fn rule(&mut self, block: &Value) -> Result<Rule, SchemaError> {
+		let mut rule = Rule::new();
+		// The range implied by a `Type` like u16, if `Min` and `Max` don't
+		// say otherwise
+		let mut implied = (None, None);
+
+		for value in block.values() {
+			let directive = value.key.as_str();
+			let duplicate = || error(value, SchemaErrorKind::Duplicate(directive.to_owned()));
+
+			match directive {
+				"Type" | "OneOf" | "Pattern" if rule.kind.is_some() => return Err(duplicate()),
+				"Type" => {
+					let name = required_value(value)?;
+					let (kind, low, high) = type_named(name)
+						.ok_or_else(|| error(value, SchemaErrorKind::UnknownType(name.into())))?;
+
+					rule.kind = Some(kind);
+					implied = (low, high);
+				}
+				"OneOf" => {
+					let options = required_value(value)?;
+					let options = options.split_whitespace().map(<_>::to_owned).collect();
+					rule.kind = Some(Kind::OneOf(options));
+				}
+				"Pattern" => {
+					let pattern = Pattern::new(required_value(value)?)
+						.map_err(|e| error(value, SchemaErrorKind::InvalidPattern(e)))?;
+					rule.kind = Some(Kind::Matches(pattern));
+				}
+				"Min" | "Max" => {
+					let slot = if directive == "Min" {
+						&mut rule.low
+					} else {
+						&mut rule.high
+					};
+
+					if slot.is_some() {
+						return Err(duplicate());
+					}
+					*slot = Some(number(value)?);
+				}
+				"Required" | "AtLeast" if rule.min.is_some() => return Err(duplicate()),
+				"Required" => {
+					no_value(value)?;
+					rule.min = Some(1);
+				}
+				"AtLeast" => rule.min = Some(number(value)?),
+				"AtMost" if rule.max.is_some() => return Err(duplicate()),
+				"AtMost" => rule.max = Some(number(value)?),
+				"NoChildren" => {
+					no_value(value)?;
+					rule.children = Some(false);
+				}
+				"Strict" => {
+					no_value(value)?;
+					rule.strict = Some(true);
+				}
+				"Secret" => {
+					no_value(value)?;
+					rule.secret = Some(true);
+				}
+				"Use" if rule.like.is_some() => return Err(duplicate()),
+				"Use" => {
+					let name = required_value(value)?;
+					self.uses
+						.push((name.to_owned(), value.line.unwrap_or_default()));
+					rule.like = Some(name.to_owned());
+				}
+				"Key" => {
+					let key = required_value(value)?;
+					let child = self.rule(value)?;
+					insert(&mut rule, key, child, value)?;
+				}
+				_ => return Err(error(value, unknown(value))),
+			}
+		}
+
+		rule.low = rule.low.or(implied.0);
+		rule.high = rule.high.or(implied.1);
+		Ok(rule)
+	}
+}
+
+/// The kind, and range, a `Type` stands for.
+fn type_named(name: &str) -> Option<(Kind, Option<f64>, Option<f64>)> {
+	macro_rules! int {
+		($kind:expr, $int:ty) => {
+			($kind, Some(<$int>::MIN as f64), Some(<$int>::MAX as f64))
+		};
+	}
+
+	Some(match name {
+		"string" => (Kind::String, None, None),
+		"int" => (Kind::Int, None, None),
+		"float" => (Kind::Float, None, None),
+		"bool" => (Kind::Bool, None, None),
+		"i8" => int!(Kind::Int, i8),
+		"i16" => int!(Kind::Int, i16),
+		"i32" => int!(Kind::Int, i32),
+		"i64" => int!(Kind::Int, i64),
+		"u8" => int!(Kind::Unsigned, u8),
+		"u16" => int!(Kind::Unsigned, u16),
+		"u32" => int!(Kind::Unsigned, u32),
+		"u64" => int!(Kind::Unsigned, u64),
+		_ => return None,
+	})
+}
+
+fn insert(parent: &mut Rule, key: &str, rule: Rule, value: &Value) -> Result<(), SchemaError> {
+	if parent.rules.insert(key.to_owned(), rule).is_some() {
+		return Err(error(value, SchemaErrorKind::Duplicate(key.to_owned())));
+	}
+
+	Ok(())
+}
+
+fn required_value(value: &Value) -> Result<&str, SchemaError> {
+	value
+		.value()
+		.ok_or_else(|| error(value, SchemaErrorKind::MissingValue(value.key.clone())))
+}
+
+fn no_value(value: &Value) -> Result<(), SchemaError> {
+	match value.value() {
+		None => Ok(()),
+		Some(_) => Err(error(
+			value,
+			SchemaErrorKind::UnexpectedValue(value.key.clone()),
+		)),
+	}
+}
+
+fn number<T: FromStr>(value: &Value) -> Result<T, SchemaError> {
+	let raw = required_value(value)?;
+	raw.parse()
+		.map_err(|_| error(value, SchemaErrorKind::InvalidNumber(raw.to_owned())))
+}
+
+fn unknown(value: &Value) -> SchemaErrorKind {
+	SchemaErrorKind::UnknownDirective(value.key.clone())
+}
+
+fn error(value: &Value, kind: SchemaErrorKind) -> SchemaError {
+	SchemaError {
+		line: value.line.unwrap_or_default(),
+		kind,
+	}
+}
+
+impl From<ParseError> for SchemaError {
+	fn from(e: ParseError) -> Self {
+		Self {
+			line: e.line,
+			kind: SchemaErrorKind::Parse(e.kind),
+		}
+	}
+}
+
+impl StdError for SchemaError {}
+impl fmt::Display for SchemaError {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		if let SchemaErrorKind::Parse(kind) = &self.kind {
+			return match kind {
+				ParseErrorKind::FileReadError => write!(f, "Failed to open schema file!"),
+				_ => write!(
+					f,
+					"Schema is not a valid document: {kind:?} on line {}",
+					self.line
+				),
+			};
+		}
+
+		write!(f, "Schema line {}: ", self.line)?;
+		match &self.kind {
+			SchemaErrorKind::Parse(_) => unreachable!(),
+			SchemaErrorKind::UnknownDirective(key) => write!(f, "'{key}' doesn't belong here"),
+			SchemaErrorKind::MissingValue(key) => write!(f, "'{key}' needs a value"),
+			SchemaErrorKind::UnexpectedValue(key) => write!(f, "'{key}' doesn't take a value"),
+			SchemaErrorKind::Duplicate(key) => write!(f, "'{key}' is already given"),
+			SchemaErrorKind::InvalidNumber(raw) => write!(f, "'{raw}' is not a number"),
+			SchemaErrorKind::UnknownType(name) => write!(f, "'{name}' is not a type"),
+			SchemaErrorKind::InvalidPattern(e) => write!(f, "{e}"),
+			SchemaErrorKind::Undefined(name) => write!(f, "nothing is defined as '{name}'"),
+		}
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use crate::schema::ViolationKind;
+
+	fn load(schema: &str) -> Schema {
+		schema.parse().unwrap()
+	}
+
+	fn check(schema: &Schema, conf: &str) -> Vec<(String, Option<usize>, ViolationKind)> {
+		let conf: Confindent = conf.parse().unwrap();
+		schema
+			.validate(&conf)
+			.into_iter()
+			.map(|v| (v.path, v.line, v.kind))
+			.collect()
+	}
+
+	#[test]
+	fn loads_rules() {
+		let schema = load(
+			"Strict\n\
+			Key Port\n\tType u16\n\tRequired\n\
+			Key Mode\n\tOneOf fast slow\n\tAtMost 1\n\
+			Key Host\n\tKey Name\n\t\tPattern [a-z]+\n\t\tRequired",
+		);
+
+		assert!(check(&schema, "Port 22\nMode fast\nHost\n\tName gen").is_empty());
+		assert_eq!(
+			check(&schema, "Port 70000\nHost\n\tName Gen\nHost\nOther"),
+			vec![
+				(
+					"Port".into(),
+					Some(0),
+					ViolationKind::OutOfRange {
+						value: "70000".into(),
+						min: Some(0.0),
+						max: Some(65535.0)
+					}
+				),
+				(
+					"Host/Name".into(),
+					Some(2),
+					ViolationKind::InvalidValue {
+						expected: Kind::Matches(Pattern::new("[a-z]+").unwrap()),
+						value: "Gen".into()
+					}
+				),
+				("Host/Name".into(), Some(3), ViolationKind::Missing),
+				("Other".into(), Some(4), ViolationKind::UnknownKey),
+			]
+		);
+	}
+
+	#[test]
+	fn unsigned_types() {
+		let schema = load("Key Size\n\tType u64\nKey Small\n\tType u8");
+
+		assert!(check(&schema, "Size 18446744073709551615\nSmall 255").is_empty());
+		assert_eq!(
+			check(&schema, "Size 18446744073709551616\nSmall -1"),
+			vec![
+				(
+					"Size".into(),
+					Some(0),
+					ViolationKind::InvalidValue {
+						expected: Kind::Unsigned,
+						value: "18446744073709551616".into()
+					}
+				),
+				(
+					"Small".into(),
+					Some(1),
+					ViolationKind::InvalidValue {
+						expected: Kind::Unsigned,
+						value: "-1".into()
+					}
+				),
+			]
+		);
+	}
+
+	#[test]
+	fn recursive_definitions() {
+		let schema = load(
+			"Key Section\n\tUse Section\n\
+			Define Section\n\tStrict\n\tKey Title\n\t\tRequired\n\tKey Section\n\t\tUse Section",
+		);
+
+		let conf = "Section\n\tTitle a\n\tSection\n\t\tTitle b\n\t\tSection\n\t\t\tBody x";
+		assert_eq!(
+			check(&schema, conf),
+			vec![
+				(
+					"Section/Section/Section/Title".into(),
+					Some(4),
+					ViolationKind::Missing
+				),
+				(
+					"Section/Section/Section/Body".into(),
+					Some(5),
+					ViolationKind::UnknownKey
+				),
+			]
+		);
+	}
+
+	#[test]
+	fn errors_have_lines() {
+		let err = |schema: &str| schema.parse::<Schema>().unwrap_err();
+
+		assert_eq!(
+			err("Key Port\n\tType short"),
+			SchemaError {
+				line: 1,
+				kind: SchemaErrorKind::UnknownType("short".into())
+			}
+		);
+		assert_eq!(
+			err("Key A\n\tUse Missing"),
+			SchemaError {
+				line: 1,
+				kind: SchemaErrorKind::Undefined("Missing".into())
+			}
+		);
+		assert_eq!(
+			err("Key A\n\tRequired\n\tAtLeast 2").kind,
+			SchemaErrorKind::Duplicate("AtLeast".into())
+		);
+		assert_eq!(err("Key A\n\tMin ten").line, 1);
+		assert_eq!(err("Key A\nKey A").line, 1);
+		assert_eq!(
+			err("Key\n").kind,
+			SchemaErrorKind::MissingValue("Key".into())
+		);
+		assert_eq!(
+			err("Key A\n\tPattern (a").kind,
+			SchemaErrorKind::InvalidPattern(PatternError {
+				position: 2,
+				message: "unclosed group"
+			})
+		);
+		assert_eq!(
+			err("Key A\n\tType int\n  Spaced"),
+			SchemaError {
+				line: 2,
+				kind: SchemaErrorKind::Parse(ParseErrorKind::SpacesWithTabs)
+			}
+		);
+	}
+}
@@ -0,0 +1,601 @@
+use core::fmt;
+use std::error::Error as StdError;
+
+/// A small regular expression that a whole value has to match.
+///
+/// This supports the common parts of regular expressions: literals, `.`,
+/// character classes like `[a-z_]` and `[^,]`, the escapes `\d`, `\w`, `\s`
+/// (and their uppercase opposites), groups with `(...)` and `|`, the
+/// quantifiers `*`, `+`, `?`, `{n}`, `{n,}`, and `{n,m}`, and the anchors
+/// `^` and `$`. Patterns always have to match the entire value, so the
+/// anchors are only there for your peace of mind.
+///
+/// # Example
+///
+/// ```rust
+/// use confindent::schema::Pattern;
+///
+/// let pattern = Pattern::new(r"[a-z]+(-[a-z0-9]+)*\.example\.com").unwrap();
+///
+/// assert!(pattern.matches("db-1.example.com"));
+/// assert!(!pattern.matches("DB.example.com"));
+/// ```
+#[derive(Clone, Debug)]
+pub struct Pattern {
+	source: String,
+	program: Vec<Step>,
+}
+
+/// The error returned when a [Pattern] can't be understood.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PatternError {
+	/// The character position the problem was found at
+	pub position: usize,
+	pub message: &'static str,
+}
+
+impl StdError for PatternError {}
+impl fmt::Display for PatternError {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		write!(
+			f,
+			"Invalid pattern, {} at character {}",
+			self.message, self.position
+		)
+	}
+}
+
+#[derive(Clone, Debug)]
+struct Alternation(Vec<Vec<Piece>>);
+
+#[derive(Clone, Debug)]
+struct Piece {
+	atom: Atom,
+	min: usize,
+	max: Option<usize>,
+}
+
+#[derive(Clone, Debug)]
+enum Atom {
+	Char(char),
+	Any,
+	Class {
+		ranges: Vec<(char, char)>,
+		negated: bool,
+	},
+	Group(Alternation),
+	Start,
+	End,
+}
+
+impl Pattern {
+	pub fn new<S: Into<String>>(pattern: S) -> Result<Self, PatternError> {
+		let source = pattern.into();
+		let chars: Vec<char> = source.chars().collect();
+
+		let mut parser = PatternParser {
+			chars: &chars,
+			pos: 0,
+		};
+		let root = parser.alternation()?;
+
+		if parser.pos < chars.len() {
+			return Err(parser.error("unmatched closing parenthesis"));
+		}
+
+		let program =
+			Compiler::compile(&root).ok_or_else(|| parser.error("too much repetition"))?;
+		Ok(Self { source, program })
+	}
+
+	/// The pattern as it was written.
+	pub fn as_str(&self) -> &str {
+		&self.source
+	}
+
+	/// Whether the entire string matches the pattern.
+	///
+	/// This takes time in proportion to the length of the string times the
+	/// length of the pattern, however the pattern is written.
+	pub fn matches(&self, s: &str) -> bool {
+		run(&self.program, s)
+	}
+}
+
+// Two patterns are the same if they were written the same.
+impl PartialEq for Pattern {
+	fn eq(&self, other: &Self) -> bool {
+		self.source == other.source
+	}
+}
+
+impl fmt::Display for Pattern {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.write_str(&self.source)
+	}
+}
+
+struct PatternParser<'a> {
+	chars: &'a [char],
+	pos: usize,
+}
+
+impl PatternParser<'_> {
+	/// An error at the character we're about to read, or the end.
+	fn error(&self, message: &'static str) -> PatternError {
+		self.error_at(self.pos, message)
+	}
+
+	fn error_at(&self, position: usize, message: &'static str) -> PatternError {
+		PatternError { position, message }
+	}
+
+	fn peek(&self) -> Option<char> {
+		self.chars.get(self.pos).copied()
+	}
+
+	/// The next character, staying at the end once we're there.
+	fn next(&mut self) -> Option<char> {
+		let ch = self.peek();
+		if ch.is_some() {
+			self.pos += 1;
+		}
+		ch
+	}
+
+	fn alternation(&mut self) -> Result<Alternation, PatternError> {
+		let mut branches = vec![self.sequence()?];
+		while self.peek() == Some('|') {
+			self.pos += 1;
+			branches.push(self.sequence()?);
+		}
+
+		Ok(Alternation(branches))
+	}
+
+	fn sequence(&mut self) -> Result<Vec<Piece>, PatternError> {
+		let mut pieces = vec![];
+
+		while let Some(ch) = self.peek() {
+			if ch == '|' || ch == ')' {
+				break;
+			}
+
+			let atom = self.atom()?;
+			let (min, max) = self.quantifier()?;
+			pieces.push(Piece { atom, min, max });
+		}
+
+		Ok(pieces)
+	}
+
+	fn atom(&mut self) -> Result<Atom, PatternError> {
+		let start = self.pos;
+		match self.next() {
+			Some('(') => {
+				// Everything is non-capturing, so this is just a group
+				if self.peek() == Some('?') {
+					self.pos += 1;
+					if self.peek() != Some(':') {
+						return Err(self.error("unsupported group"));
+					}
+					self.pos += 1;
+				}
+
+				let group = self.alternation()?;
+				if self.peek() != Some(')') {
+					return Err(self.error("unclosed group"));
+				}
+				self.pos += 1;
+				Ok(Atom::Group(group))
+			}
+			Some('[') => self.class(),
+			Some('.') => Ok(Atom::Any),
+			Some('^') => Ok(Atom::Start),
+			Some('$') => Ok(Atom::End),
+			Some('*') | Some('+') | Some('?') | Some('{') => {
+				Err(self.error_at(start, "quantifier without anything to repeat"))
+			}
+			Some('\\') => self.escape(start),
+			Some(ch) => Ok(Atom::Char(ch)),
+			None => unreachable!(),
+		}
+	}
+
+	/// The escape after the backslash at `start`.
+	fn escape(&mut self, start: usize) -> Result<Atom, PatternError> {
+		let ch = self
+			.next()
+			.ok_or_else(|| self.error_at(start, "trailing backslash"))?;
+
+		Ok(match class_escape(ch) {
+			Some(ranges) => Atom::Class {
+				ranges: ranges.to_vec(),
+				negated: ch.is_ascii_uppercase(),
+			},
+			None => Atom::Char(ch),
+		})
+	}
+
+	fn class(&mut self) -> Result<Atom, PatternError> {
+		let negated = self.peek() == Some('^');
+		if negated {
+			self.pos += 1;
+		}
+
+		let mut ranges = vec![];
+		let mut first = true;
+		loop {
+			let start = self.pos;
+			let ch = match self.next() {
+				None => return Err(self.error("unclosed character class")),
+				Some(']') if !first => break,
+				Some('\\') => {
+					let escaped = self
+						.next()
+						.ok_or_else(|| self.error_at(start, "trailing backslash"))?;
+					match class_escape(escaped) {
+						Some(class) if escaped.is_ascii_lowercase() => {
+							ranges.extend_from_slice(class);
+							first = false;
+							continue;
+						}
+						Some(_) => {
+							return Err(self.error_at(start, "negated escape in character class"))
+						}
+						None => escaped,
+					}
+				}
+				Some(ch) => ch,
+			};
+			first = false;
+
+			if self.peek() == Some('-') && self.chars.get(self.pos + 1) != Some(&']') {
+				self.pos += 1;
+				let backslash = self.pos;
+				let end = match self.next() {
+					None => return Err(self.error("unclosed character class")),
+					Some('\\') => self
+						.next()
+						.ok_or_else(|| self.error_at(backslash, "trailing backslash"))?,
+					Some(end) => end,
+				};
+
+				if end < ch {
+					return Err(self.error_at(start, "character range is backwards"));
+				}
+				ranges.push((ch, end));
+			} else {
+				ranges.push((ch, ch));
+			}
+		}
+
+		Ok(Atom::Class { ranges, negated })
+	}
+
+	fn quantifier(&mut self) -> Result<(usize, Option<usize>), PatternError> {
+		let quantifier = match self.peek() {
+			Some('*') => (0, None),
+			Some('+') => (1, None),
+			Some('?') => (0, Some(1)),
+			Some('{') => {
+				let start = self.pos;
+				self.pos += 1;
+				let min = self
+					.number()
+					.ok_or_else(|| self.error("expected a number"))?;
+
+				let max = match self.peek() {
+					Some('}') => {
+						self.pos += 1;
+						return Ok((min, Some(min)));
+					}
+					Some(',') => {
+						self.pos += 1;
+						self.number()
+					}
+					_ => return Err(self.error("unclosed repetition")),
+				};
+
+				if self.peek() != Some('}') {
+					return Err(self.error("unclosed repetition"));
+				} else if max.map(|max| max < min).unwrap_or(false) {
+					return Err(self.error_at(start, "repetition range is backwards"));
+				}
+
+				(min, max)
+			}
+			_ => return Ok((1, Some(1))),
+		};
+
+		self.pos += 1;
+		Ok(quantifier)
+	}
+
+	fn number(&mut self) -> Option<usize> {
+		let start = self.pos;
+		while self.peek().map(|ch| ch.is_ascii_digit()).unwrap_or(false) {
+			self.pos += 1;
+		}
+
+		let digits: String = self.chars[start..self.pos].iter().collect();
+		digits.parse().ok()
+	}
+}
+
+/// The ranges of characters an escape like `\d` stands for, ignoring case.
+fn class_escape(ch: char) -> Option<&'static [(char, char)]> {
+	match ch.to_ascii_lowercase() {
+		'd' => Some(&[('0', '9')]),
+		'w' => Some(&[('a', 'z'), ('A', 'Z'), ('0', '9'), ('_', '_')]),
+		's' => Some(&[(' ', ' '), ('\t', '\r')]),
+		_ => None,
+	}
+}
+
+/// One step of a compiled pattern. Matching follows every way through the
+/// steps at once, a character at a time, so it takes as long as the value
+/// times the pattern and never backtracks.
+#[derive(Clone, Debug)]
+enum Step {
+	Char(char),
+	Any,
+	Class {
+		ranges: Vec<(char, char)>,
+		negated: bool,
+	},
+	/// Carry on at both steps
+	Split(usize, usize),
+	Jump(usize),
+	Start,
+	End,
+	Match,
+}
+
+/// Compiles a parsed pattern into [Step]s.
+struct Compiler {
+	steps: Vec<Step>,
+}
+
+impl Compiler {
+	/// Repetitions are written out in full, so this keeps something like
+	/// `(a{1000}){1000}` from taking all the memory there is.
+	const MAX_STEPS: usize = 100_000;
+
+	fn compile(root: &Alternation) -> Option<Vec<Step>> {
+		let mut compiler = Compiler { steps: vec![] };
+		compiler.alternation(root)?;
+		compiler.push(Step::Match)?;
+		Some(compiler.steps)
+	}
+
+	fn push(&mut self, step: Step) -> Option<usize> {
+		if self.steps.len() >= Self::MAX_STEPS {
+			return None;
+		}
+
+		self.steps.push(step);
+		Some(self.steps.len() - 1)
+	}
+
+	fn alternation(&mut self, alt: &Alternation) -> Option<()> {
+		let mut jumps = vec![];
+
+		for (idx, branch) in alt.0.iter().enumerate() {
+			let last = idx == alt.0.len() - 1;
+			let split = match last {
+				true => None,
+				false => Some(self.push(Step::Split(0, 0))?),
+			};
+
+			for piece in branch {
+				self.piece(piece)?;
+			}
+
+			if let Some(split) = split {
+				jumps.push(self.push(Step::Jump(0))?);
+				self.steps[split] = Step::Split(split + 1, self.steps.len());
+			}
+		}
+
+		let end = self.steps.len();
+		for jump in jumps {
+			self.steps[jump] = Step::Jump(end);
+		}
+		Some(())
+	}
+
+	fn piece(&mut self, piece: &Piece) -> Option<()> {
+		for _ in 0..piece.min {
+			self.atom(&piece.atom)?;
+		}
+
+		match piece.max {
+			None => {
+				let split = self.push(Step::Split(0, 0))?;
+				self.atom(&piece.atom)?;
+				self.push(Step::Jump(split))?;
+				self.steps[split] = Step::Split(split + 1, self.steps.len());
+			}
+			Some(max) => {
+				// Each optional one can skip to the end of all of them
+				let mut splits = vec![];
+				for _ in piece.min..max {
+					splits.push(self.push(Step::Split(0, 0))?);
+					self.atom(&piece.atom)?;
+				}
+
+				let end = self.steps.len();
+				for split in splits {
+					self.steps[split] = Step::Split(split + 1, end);
+				}
+			}
+		}
+
+		Some(())
+	}
+
+	fn atom(&mut self, atom: &Atom) -> Option<()> {
+		let step = match atom {
+			Atom::Char(ch) => Step::Char(*ch),
+			Atom::Any => Step::Any,
+			Atom::Class { ranges, negated } => Step::Class {
+				ranges: ranges.clone(),
+				negated: *negated,
+			},
+			Atom::Group(alt) => return self.alternation(alt),
+			Atom::Start => Step::Start,
+			Atom::End => Step::End,
+		};
+
+		self.push(step).map(|_| ())
+	}
+}
+
+/// The steps that are waiting for the next character, each only once.
+struct Threads {
+	steps: Vec<usize>,
+	/// Whether each step was added for this character
+	seen: Vec<bool>,
+}
+
+impl Threads {
+	fn new(len: usize) -> Self {
+		Self {
+			steps: vec![],
+			seen: vec![false; len],
+		}
+	}
+
+	fn clear(&mut self) {
+		self.steps.clear();
+		self.seen.fill(false);
+	}
+
+	/// Add `step`, and every step it can get to without a character, at
+	/// `pos` in a value `len` characters long.
+	fn add(&mut self, program: &[Step], step: usize, pos: usize, len: usize) {
+		let mut stack = vec![step];
+
+		while let Some(step) = stack.pop() {
+			if std::mem::replace(&mut self.seen[step], true) {
+				continue;
+			}
+
+			match program[step] {
+				Step::Jump(to) => stack.push(to),
+				Step::Split(first, second) => stack.extend([second, first]),
+				Step::Start if pos == 0 => stack.push(step + 1),
+				Step::End if pos == len => stack.push(step + 1),
+				Step::Start | Step::End => (),
+				_ => self.steps.push(step),
+			}
+		}
+	}
+}
+
+/// Whether the whole of `s` gets through the program.
+fn run(program: &[Step], s: &str) -> bool {
+	let len = s.chars().count();
+	let mut current = Threads::new(program.len());
+	let mut next = Threads::new(program.len());
+	current.add(program, 0, 0, len);
+
+	for (pos, ch) in s.chars().enumerate() {
+		if current.steps.is_empty() {
+			return false;
+		}
+
+		next.clear();
+		for &step in &current.steps {
+			let matched = match &program[step] {
+				Step::Char(expected) => *expected == ch,
+				Step::Any => true,
+				Step::Class { ranges, negated } => {
+					ranges.iter().any(|(lo, hi)| *lo <= ch && ch <= *hi) != *negated
+				}
+				_ => false,
+			};
+
+			if matched {
+				next.add(program, step + 1, pos + 1, len);
+			}
+		}
+
+		std::mem::swap(&mut current, &mut next);
+	}
+
+	current
+		.steps
+		.iter()
+		.any(|step| matches!(program[*step], Step::Match))
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	fn matches(pattern: &str, s: &str) -> bool {
+		Pattern::new(pattern).unwrap().matches(s)
+	}
+
+	#[test]
+	fn literals_and_classes() {
+		assert!(matches("abc", "abc"));
+		assert!(!matches("abc", "abcd"));
+		assert!(matches("a.c", "a-c"));
+		assert!(matches(r"\d+", "8080"));
+		assert!(!matches(r"\d+", "80a"));
+		assert!(matches("[a-c_]*", "ab_ca"));
+		assert!(matches("[^,]+", "no commas"));
+		assert!(!matches("[^,]+", "a,b"));
+		assert!(matches(r"[\w.-]+", "db-1.local"));
+	}
+
+	#[test]
+	fn groups_and_repetition() {
+		assert!(matches("(ab|cd)+", "abcdab"));
+		assert!(!matches("(ab|cd)+", "abc"));
+		assert!(matches("^a{2,3}$", "aaa"));
+		assert!(!matches("a{2,3}", "aaaa"));
+		assert!(matches("x(?:y?)*z", "xz"));
+		assert!(matches("colou?r", "color"));
+	}
+
+	#[test]
+	fn long_values_and_nested_repetition() {
+		let long = "x".repeat(100_000);
+		assert!(matches(".*", &long));
+		assert!(matches("x+", &long));
+		assert!(!matches("x*y", &long));
+
+		let a = "a".repeat(100_000);
+		assert!(!matches("(a+)+b", &a));
+		assert!(matches("(a+)+", &a));
+		assert!(!matches("(a|aa)*(b|c)", &a[..30]));
+		assert!(!matches("(x*)*y", &long));
+	}
+
+	#[test]
+	fn bad_patterns() {
+		assert!(Pattern::new("(ab").is_err());
+		assert!(Pattern::new("ab)").is_err());
+		assert!(Pattern::new("[ab").is_err());
+		assert!(Pattern::new("*a").is_err());
+		assert!(Pattern::new("a{3,1}").is_err());
+		assert!(Pattern::new("[z-a]").is_err());
+		assert!(Pattern::new("(a{1000}){1000}").is_err());
+
+		// Errors point at what's wrong, or the end if something's missing
+		let at = |pattern| Pattern::new(pattern).unwrap_err().position;
+		assert_eq!(at("(a"), 2);
+		assert_eq!(at("ab)"), 2);
+		assert_eq!(at("[ab"), 3);
+		assert_eq!(at("a|*"), 2);
+		assert_eq!(at("ab\\"), 2);
+		assert_eq!(at("a[z-a]"), 2);
+		assert_eq!(at("a{3,1}"), 1);
+		assert_eq!(at("a{3"), 3);
+		assert_eq!(at("(?x)"), 2);
+	}
+}
@@ -0,0 +1,225 @@
+//! Keeping passwords and the like out of logs.
+
+use core::fmt;
+
+use crate::{index::KeyIndex, line::Line, Confindent, Value};
+
+/// What a secret value is printed as.
+pub(crate) const REDACTED: &str = "<redacted>";
+
+/// A value that isn't printed, and is cleared from memory when it's
+/// dropped. Get one with [Value::secret] or [Confindent::get_secret].
+///
+/// Printing it, with `{}` or `{:?}`, prints `<redacted>`. Use
+/// [expose](Secret::expose) to get at the value.
+///
+/// # Example
+///
+/// ```rust
+/// use confindent::Confindent;
+///
+/// let conf: Confindent = "Database\n\tPassword hunter2".parse().unwrap();
+/// let password = conf.get_secret("Database/Password").unwrap();
+///
+/// assert_eq!(password.expose(), "hunter2");
+/// assert_eq!(format!("{password:?}"), "<redacted>");
+/// ```
+#[derive(Clone, PartialEq, Eq)]
+pub struct Secret {
+	value: String,
+}
+
+impl Secret {
+	pub fn new<S: Into<String>>(value: S) -> Self {
+		Self {
+			value: value.into(),
+		}
+	}
+
+	/// The secret itself. Careful where you put it.
+	pub fn expose(&self) -> &str {
+		&self.value
+	}
+}
+
+impl Drop for Secret {
+	fn drop(&mut self) {
+		let mut bytes = std::mem::take(&mut self.value).into_bytes();
+		// Clear all of the allocation, not just what's in use
+		bytes.resize(bytes.capacity(), 0);
+		bytes.fill(0);
+		// Don't let the compiler decide the writes are pointless
+		std::hint::black_box(&bytes);
+	}
+}
+
+impl fmt::Debug for Secret {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.write_str(REDACTED)
+	}
+}
+
+impl fmt::Display for Secret {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.write_str(REDACTED)
+	}
+}
+
+impl Confindent {
+	/// Mark every value at a `/` separated path of keys as secret. Where
+	/// there's more than one value at the path, they're all marked.
+	///
+	/// Secret values are printed as `<redacted>`, whether the document's
+	/// printed with `{}` or `{:?}` or turned into JSON, and aren't in the
+	/// errors from reading them. They can still be read, and they're saved
+	/// as they are. See [Value::set_secret].
+	///
+	/// # Example
+	///
+	/// ```rust
+	/// use confindent::Confindent;
+	///
+	/// let mut conf: Confindent = "Database\n\tUser gen\n\tPassword hunter2".parse().unwrap();
+	/// conf.mark_secret("Database/Password");
+	///
+	/// assert_eq!(conf.to_string(), "Database\n\tUser gen\n\tPassword <redacted>");
+	/// assert_eq!(conf.get("Database/Password"), Some("hunter2"));
+	/// ```
+	pub fn mark_secret<S: AsRef<str>>(&mut self, path: S) {
+		mark_path(&mut self.children, &self.index, path.as_ref());
+	}
+
+	/// Mark every value whose key starts with `prefix` as secret, like
+	/// `SecretToken` with a prefix of `Secret`. See [mark_secret](Confindent::mark_secret).
+	pub fn mark_prefixed_secrets<S: AsRef<str>>(&mut self, prefix: S) {
+		let prefix = prefix.as_ref();
+		self.mark_secrets(|_, value| value.key().starts_with(prefix));
+	}
+
+	/// Mark every value `f` says is secret. It's given each value and its
+	/// `/` separated path. See [mark_secret](Confindent::mark_secret).
+	///
+	/// Only the values in the document now are marked, so values added
+	/// later have to be marked themselves.
+	///
+	/// # Example
+	///
+	/// ```rust
+	/// use confindent::Confindent;
+	///
+	/// let mut conf: Confindent = "Api\n\tToken abc\nUser gen\n\tPassword hunter2".parse().unwrap();
+	/// conf.mark_secrets(|path, _| path.ends_with("Token") || path.ends_with("Password"));
+	///
+	/// assert!(conf.child("Api").unwrap().child("Token").unwrap().is_secret());
+	/// assert_eq!(conf.to_string(), "Api\n\tToken <redacted>\nUser gen\n\tPassword <redacted>");
+	/// ```
+	pub fn mark_secrets<F: FnMut(&str, &Value) -> bool>(&mut self, mut f: F) {
+		mark_where(&mut self.children, "", &mut f);
+	}
+
+	/// Get the value at a `/` separated path as a [Secret], whether or not
+	/// it's been marked as one.
+	pub fn get_secret<S: AsRef<str>>(&self, path: S) -> Option<Secret> {
+		self.get(path).map(Secret::new)
+	}
+}
+
+fn mark_path(lines: &mut [Line], index: &KeyIndex, path: &str) {
+	let (key, rest) = match path.split_once('/') {
+		None => (path, None),
+		Some((key, rest)) => (key, Some(rest)),
+	};
+
+	for line in lines {
+		let Line::Value(value) = line else {
+			continue;
+		};
+		if !index.matches(value, key) {
+			continue;
+		}
+
+		match rest {
+			None => value.secret = true,
+			Some(rest) => mark_path(&mut value.children, &value.index, rest),
+		}
+	}
+}
+
+fn mark_where<F: FnMut(&str, &Value) -> bool>(lines: &mut [Line], parent: &str, f: &mut F) {
+	for line in lines {
+		let Line::Value(value) = line else {
+			continue;
+		};
+
+		let path = match parent {
+			"" => value.key.clone(),
+			parent => format!("{parent}/{}", value.key),
+		};
+		if f(&path, value) {
+			value.secret = true;
+		}
+		mark_where(&mut value.children, &path, f);
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use crate::{
+		schema::{Rule, Schema},
+		Delimiter,
+	};
+
+	#[test]
+	fn redacted_everywhere() {
+		let text = "Db\r\n\tUser  gen\r\n\tPassword hunter2\r\nSecretToken abc\r\n";
+		let mut conf: Confindent = text.parse().unwrap();
+		conf.mark_secret("Db/Password");
+		conf.mark_prefixed_secrets("Secret");
+
+		let printed = conf.to_string();
+		assert_eq!(
+			printed,
+			"Db\r\n\tUser  gen\r\n\tPassword <redacted>\r\nSecretToken <redacted>\r\n"
+		);
+		assert!(!format!("{conf:?}").contains("hunter2"));
+		assert!(!conf.to_json().contains("abc"));
+		assert_eq!(conf.unredacted().to_string(), text);
+
+		let err = conf.get_parse::<_, u8>("Db/Password").unwrap_err();
+		assert_eq!(err.raw.as_deref(), Some(REDACTED));
+		assert!(!err.to_string().contains("hunter2"));
+
+		// The mark goes where the value goes
+		let password = conf.child_mut("Db").unwrap().remove("Password").unwrap();
+		assert_eq!(password.to_string(), "\tPassword <redacted>\n");
+		assert_eq!(password.secret().unwrap().expose(), "hunter2");
+	}
+
+	#[test]
+	fn redacted_in_errors() {
+		let mut conf: Confindent = "Pins 1234, x5678\nPin 9999".parse().unwrap();
+		conf.mark_secret("Pins");
+		conf.mark_secret("Pin");
+
+		let err = conf
+			.child("Pins")
+			.unwrap()
+			.parse_list::<u16>(Delimiter::Comma)
+			.unwrap_err();
+		assert_eq!(err.raw.as_deref(), Some(REDACTED));
+		let printed = err.to_string();
+		assert!(
+			!printed.contains("1234") && !printed.contains("5678"),
+			"{printed}"
+		);
+
+		let schema = Schema::new().rule("Pin", Rule::new().max(1000.0));
+		let violations = schema.validate(&conf);
+		let printed = violations[0].to_string();
+		assert!(
+			printed.contains(REDACTED) && !printed.contains("9999"),
+			"{printed}"
+		);
+	}
+}
@@ -0,0 +1,242 @@
+//! Reading OpenSSH client configs, like `~/.ssh/config`.
+
+use crate::{
+	error::ParseError,
+	indent::Indent,
+	index::KeyIndex,
+	line::{Line, LineEnding},
+	Confindent, KeyCase, Value,
+};
+
+impl Confindent {
+	/// Read an OpenSSH client config, like `~/.ssh/config`, which is close
+	/// to a document but not quite one.
+	///
+	/// Everything after a `Host` or `Match` line, up to the next one, is a
+	/// child of it, whether it's indented or not, and whatever case `Host`
+	/// and `Match` are written in. A keyword can be followed by whitespace,
+	/// an `=`, or both. Lines can be indented any way at all, tabs and spaces
+	/// mixed too, since ssh doesn't look. Blank lines end a block, so a blank line and any
+	/// comments after it at the end of one go between it and the next.
+	///
+	/// Keywords are looked up without caring about their ASCII case, like
+	/// ssh does, so `port` finds `Port`. See [KeyCase](crate::KeyCase).
+	///
+	/// Arguments are kept as they're written, quotes and all. Use
+	/// [words](Value::words) to split one up like ssh does.
+	///
+	/// Everything is kept as it was, so printing the document writes back
+	/// the same config, and anything you add is written the way ssh reads
+	/// it. Call [normalize](Confindent::normalize) if you want it indented
+	/// like a document instead.
+	///
+	/// # Example
+	///
+	/// ```rust
+	/// use confindent::Confindent;
+	///
+	/// let config = "Include ~/.ssh/more\n\nHost example.com\nUser=gen\n\tPort = 2222\n";
+	/// let conf = Confindent::from_ssh_config(config).unwrap();
+	///
+	/// let host = conf.child("Host").unwrap();
+	/// assert_eq!(host.child_value("User"), Some("gen"));
+	/// assert_eq!(host.child_value("Port"), Some("2222"));
+	/// assert_eq!(conf.to_string(), config);
+	/// ```
+	pub fn from_ssh_config(config: &str) -> Result<Self, ParseError> {
+		let mut conf = Confindent::new();
+		let mut block: Option<Value> = None;
+
+		let mut rest = config;
+		if let Some(stripped) = rest.strip_prefix('\u{feff}') {
+			conf.bom = true;
+			rest = stripped;
+		}
+
+		let mut number = 0;
+		while !rest.is_empty() {
+			let (raw, ending, remainder) = LineEnding::split_line(rest);
+			rest = remainder;
+
+			conf.final_newline = ending.is_some();
+			if number == 0 {
+				conf.line_ending = ending.unwrap_or_default();
+			}
+			// We only keep line endings that differ from the document's
+			let ending = ending.filter(|ending| *ending != conf.line_ending);
+
+			match split(raw, ending, number) {
+				Line::Value(value) if starts_block(&value.key) => {
+					if let Some(block) = block.replace(value) {
+						end_block(&mut conf.children, block);
+					}
+				}
+				line => match &mut block {
+					None => conf.children.push(line),
+					Some(block) => block.children.push(line),
+				},
+			}
+
+			number += 1;
+		}
+
+		if let Some(block) = block {
+			end_block(&mut conf.children, block);
+		}
+		conf.set_key_case(KeyCase::IgnoreAscii);
+
+		Ok(conf)
+	}
+}
+
+fn starts_block(keyword: &str) -> bool {
+	keyword.eq_ignore_ascii_case("Host") || keyword.eq_ignore_ascii_case("Match")
+}
+
+/// Add a block to the document. The first blank line after its last value,
+/// and everything after that, goes after it instead of in it.
+fn end_block(lines: &mut Vec<Line>, mut block: Value) {
+	let last_value = block
+		.children
+		.iter()
+		.rposition(|line| matches!(line, Line::Value(_)))
+		.map_or(0, |idx| idx + 1);
+	let blank = block.children[last_value..]
+		.iter()
+		.position(|line| matches!(line, Line::Blank { .. }));
+
+	let after = match blank {
+		None => vec![],
+		Some(blank) => block.children.split_off(last_value + blank),
+	};
+
+	lines.push(Line::Value(block));
+	lines.extend(after);
+}
+
+/// Split a line of the config into its parts, keeping the indent as it is
+/// even if the line is in a block.
+fn split(raw: &str, ending: Option<LineEnding>, number: usize) -> Line {
+	if raw.trim().is_empty() {
+		return Line::Blank {
+			whitespace: raw.to_owned(),
+			ending,
+		};
+	}
+
+	// ssh doesn't care how lines are indented, so one that mixes tabs and
+	// spaces is kept as it was written. Tabs reach every eighth column, like
+	// a terminal shows them
+	let (whitespace, other) = raw.split_at(Value::whitespace_end_index(raw));
+	let (indent, written_indent) = match whitespace.parse() {
+		Ok(indent) => (indent, None),
+		Err(_) => (
+			Indent::from_mixed(whitespace, 8),
+			Some(whitespace.to_owned()),
+		),
+	};
+
+	if let Some(comment) = other.strip_prefix('#') {
+		return Line::Comment {
+			indent,
+			written_indent,
+			prefix: None,
+			comment: comment.to_owned(),
+			ending,
+		};
+	}
+
+	// The keyword ends at whitespace or an =, and the argument starts after
+	// some whitespace, an =, or both
+	let key_end = other
+		.find(|c: char| c.is_whitespace() || c == '=')
+		.unwrap_or(other.len());
+	let (key, after) = other.split_at(key_end);
+
+	let spaces = |s: &str| s.len() - s.trim_start().len();
+	let mut separator_end = spaces(after);
+	if after[separator_end..].starts_with('=') {
+		separator_end += 1;
+		separator_end += spaces(&after[separator_end..]);
+	}
+	let (separator, value) = after.split_at(separator_end);
+
+	let value = Some(value).filter(|value| !value.is_empty());
+	let separator = match (separator, value) {
+		(" ", Some(_)) | ("", None) => None,
+		(separator, _) => Some(separator.to_owned()),
+	};
+
+	Line::Value(Value {
+		indent,
+		key: key.to_owned(),
+		value: value.map(<_>::to_owned),
+		separator,
+		ending,
+		line: Some(number),
+		children: vec![],
+		index: KeyIndex::default(),
+		secret: false,
+		inherited: false,
+		written_indent,
+	})
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	const CONFIG: &str = "# global\r\nInclude config.d/*\r\nServerAliveInterval=60\r\n\r\nhost github.com gitlab.com\r\n    User git\r\n    IdentityFile \"~/.ssh/my key\"\r\n    # still github\r\n\r\n# the rest\r\nMatch host *.internal exec \"test -f ~/.vpn\"\r\nProxyJump\t=  bastion\r\nForwardAgent yes";
+
+	#[test]
+	fn blocks() {
+		let conf = Confindent::from_ssh_config(CONFIG).unwrap();
+
+		let keys: Vec<&str> = conf.values().map(|value| value.key()).collect();
+		assert_eq!(keys, ["Include", "ServerAliveInterval", "host", "Match"]);
+		assert_eq!(conf.get("ServerAliveInterval"), Some("60"));
+
+		let host = conf.child("host").unwrap();
+		assert_eq!(host.value(), Some("github.com gitlab.com"));
+		assert_eq!(
+			host.child("IdentityFile").unwrap().words().unwrap(),
+			["~/.ssh/my key"]
+		);
+
+		assert_eq!(conf.get("HOST/user"), Some("git"));
+
+		let matched = conf.child("Match").unwrap();
+		assert_eq!(matched.child_value("ProxyJump"), Some("bastion"));
+		assert_eq!(matched.child_value("ForwardAgent"), Some("yes"));
+	}
+
+	#[test]
+	fn writes_back() {
+		let mut conf = Confindent::from_ssh_config(CONFIG).unwrap();
+		assert_eq!(conf.to_string(), CONFIG);
+
+		conf.child_mut("Match")
+			.unwrap()
+			.add_child(Value::new("Port", Some(22)));
+		assert!(conf
+			.to_string()
+			.ends_with("ForwardAgent yes\r\nPort\t=  22"));
+
+		conf.normalize();
+		assert_eq!(
+			conf.to_string(),
+			"# global\r\nInclude config.d/*\r\nServerAliveInterval=60\r\n\r\nhost github.com gitlab.com\r\n\tUser git\r\n\tIdentityFile \"~/.ssh/my key\"\r\n\t# still github\r\n\r\n# the rest\r\nMatch host *.internal exec \"test -f ~/.vpn\"\r\n\tProxyJump\t=  bastion\r\n\tForwardAgent yes\r\n\tPort\t=  22"
+		);
+	}
+
+	#[test]
+	fn mixed_indent() {
+		let config = "Host a\n\t User b\n \t# c\n";
+		let mut conf = Confindent::from_ssh_config(config).unwrap();
+
+		assert_eq!(conf.get("Host/User"), Some("b"));
+		assert_eq!(conf.to_string(), config);
+		conf.normalize();
+		assert_eq!(conf.to_string(), "Host a\n\tUser b\n\t# c\n");
+	}
+}
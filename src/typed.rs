@@ -0,0 +1,490 @@
+//! Parsing for the kinds of values configuration files are full of that
+//! [FromStr](std::str::FromStr) doesn't handle the way people write them.
+
+use core::fmt;
+use std::{str::FromStr, time::Duration};
+
+use crate::{
+	error::{ValueError, ValueErrorKind},
+	index::{Children, KeyIndex},
+	line::Line,
+	secret::REDACTED,
+	Value, ValueRef,
+};
+
+/// Follow a path of keys, split by the delimiter, down from a list of lines.
+pub(crate) fn walk<'a>(
+	lines: &'a [Line],
+	index: &'a KeyIndex,
+	path: &str,
+	delimiter: char,
+) -> Option<&'a Value> {
+	let mut splits = path.split(delimiter);
+
+	let mut current = splits
+		.next()
+		.and_then(|key| Children::new(lines, index, key).next())?;
+	for key in splits {
+		current = current.child(key)?;
+	}
+
+	Some(current)
+}
+
+/// What the typed accessors need to know about a value they found.
+pub(crate) struct Found<'a> {
+	pub(crate) value: Option<&'a str>,
+	pub(crate) line: Option<usize>,
+	/// Whether to leave the value out of errors
+	pub(crate) secret: bool,
+}
+
+impl<'a> From<&'a Value> for Found<'a> {
+	fn from(value: &'a Value) -> Self {
+		Found {
+			value: value.value(),
+			line: value.line,
+			secret: value.secret,
+		}
+	}
+}
+
+impl<'a> From<&ValueRef<'a>> for Found<'a> {
+	fn from(value: &ValueRef<'a>) -> Self {
+		Found {
+			value: value.value(),
+			line: Some(value.line()),
+			secret: false,
+		}
+	}
+}
+
+/// Parse the value found at a path, or say why it couldn't be.
+pub(crate) fn typed<'a, T, F>(
+	found: Option<F>,
+	path: &str,
+	parse: fn(&str) -> Result<T, ValueErrorKind>,
+) -> Result<T, ValueError>
+where
+	F: Into<Found<'a>>,
+{
+	let found = found.map(Into::into);
+	let error = |kind, raw: Option<&str>| ValueError {
+		path: path.to_owned(),
+		line: found.as_ref().and_then(|found| found.line),
+		raw: raw.map(<_>::to_owned),
+		kind,
+	};
+
+	let found = found
+		.as_ref()
+		.ok_or_else(|| error(ValueErrorKind::MissingKey, None))?;
+	let raw = found
+		.value
+		.ok_or_else(|| error(ValueErrorKind::MissingValue, None))?;
+
+	let shown = if found.secret { REDACTED } else { raw };
+	parse(raw).map_err(|kind| error(kind, Some(shown)))
+}
+
+/// A key that's there without a value is true, one that isn't there at all
+/// is false, and otherwise the value has to be a bool.
+pub(crate) fn flag(found: Option<&Value>, path: &str) -> Result<bool, ValueError> {
+	match found {
+		None => Ok(false),
+		Some(value) if value.value().is_none() => Ok(true),
+		Some(_) => typed(found, path, parse_bool),
+	}
+}
+
+/// Parse with [FromStr], keeping what the error had to say.
+pub(crate) fn from_str<T>(s: &str) -> Result<T, ValueErrorKind>
+where
+	T: FromStr,
+	T::Err: fmt::Display,
+{
+	s.parse().map_err(|e: T::Err| ValueErrorKind::Invalid {
+		type_name: type_name::<T>(),
+		message: e.to_string(),
+	})
+}
+
+/// The name of a type without the paths of the modules it's in, so that
+/// `alloc::vec::Vec<core::net::IpAddr>` is just `Vec<IpAddr>`.
+pub(crate) fn type_name<T>() -> String {
+	let full = std::any::type_name::<T>();
+	let mut name = String::with_capacity(full.len());
+
+	let mut rest = full;
+	while let Some(start) = rest.find(|c: char| c.is_alphanumeric() || c == '_') {
+		name.push_str(&rest[..start]);
+		rest = &rest[start..];
+
+		let end = rest
+			.find(|c: char| !(c.is_alphanumeric() || c == '_' || c == ':'))
+			.unwrap_or(rest.len());
+		let path = &rest[..end];
+		name.push_str(path.rsplit("::").next().unwrap_or(path));
+		rest = &rest[end..];
+	}
+	name.push_str(rest);
+
+	name
+}
+
+pub(crate) fn parse_bool(s: &str) -> Result<bool, ValueErrorKind> {
+	const TRUE: &[&str] = &["yes", "on", "true", "1"];
+	const FALSE: &[&str] = &["no", "off", "false", "0"];
+
+	if TRUE.iter().any(|t| t.eq_ignore_ascii_case(s)) {
+		Ok(true)
+	} else if FALSE.iter().any(|f| f.eq_ignore_ascii_case(s)) {
+		Ok(false)
+	} else {
+		Err(ValueErrorKind::InvalidBool)
+	}
+}
+
+/// Durations are numbers followed by a unit, `ms`, `s`, `m`, `h`, or `d`,
+/// one after the other like `1h30m`. A number alone is seconds.
+pub(crate) fn parse_duration(s: &str) -> Result<Duration, ValueErrorKind> {
+	let s = s.trim();
+	if !s.is_empty() && s.bytes().all(|b| b.is_ascii_digit()) {
+		return number(s).map(Duration::from_secs);
+	}
+
+	let mut total = Duration::ZERO;
+	let mut rest = s;
+	while !rest.is_empty() {
+		let (digits, after) = split_at_first(rest, |c| !c.is_ascii_digit());
+		let (unit, after) = split_at_first(after.trim_start(), |c| !c.is_ascii_alphabetic());
+		rest = after.trim_start();
+
+		if digits.is_empty() {
+			return Err(ValueErrorKind::InvalidDuration);
+		}
+
+		let count = number(digits)?;
+		let millis = match unit {
+			"ms" => 1,
+			"s" => 1000,
+			"m" => 60 * 1000,
+			"h" => 60 * 60 * 1000,
+			"d" => 24 * 60 * 60 * 1000,
+			_ => return Err(ValueErrorKind::InvalidDuration),
+		};
+
+		let part = count
+			.checked_mul(millis)
+			.map(Duration::from_millis)
+			.ok_or(ValueErrorKind::TooLarge)?;
+		total = total.checked_add(part).ok_or(ValueErrorKind::TooLarge)?;
+	}
+
+	if s.is_empty() {
+		return Err(ValueErrorKind::InvalidDuration);
+	}
+	Ok(total)
+}
+
+/// Sizes are a number, maybe with a fraction, and maybe a unit. Units
+/// like `KB` are powers of 1000 and units like `KiB`, or a bare `K`, are
+/// powers of 1024. Units aren't case sensitive and a `B` alone is bytes.
+pub(crate) fn parse_bytes(s: &str) -> Result<u64, ValueErrorKind> {
+	let (number, unit) = split_at_first(s.trim(), |c| !(c.is_ascii_digit() || c == '.'));
+	let unit = unit.trim_start().to_ascii_lowercase();
+
+	let (power, binary) = match unit.as_str() {
+		"" | "b" => (0, false),
+		_ => {
+			let mut chars = unit.chars();
+			let power = match chars.next() {
+				Some('k') => 1,
+				Some('m') => 2,
+				Some('g') => 3,
+				Some('t') => 4,
+				Some('p') => 5,
+				Some('e') => 6,
+				_ => return Err(ValueErrorKind::InvalidBytes),
+			};
+
+			match chars.as_str() {
+				"" | "ib" => (power, true),
+				"b" => (power, false),
+				_ => return Err(ValueErrorKind::InvalidBytes),
+			}
+		}
+	};
+	let scale = if binary { 1024u128 } else { 1000 }.pow(power);
+
+	let (whole, fraction) = number.split_once('.').unwrap_or((number, ""));
+	let fraction_ok = fraction.len() <= 18 && fraction.bytes().all(|b| b.is_ascii_digit());
+	if whole.is_empty() || !fraction_ok {
+		return Err(ValueErrorKind::InvalidBytes);
+	}
+
+	let whole = u128::from(self::number(whole)?) * scale;
+	let fraction = match fraction {
+		"" => 0,
+		digits => {
+			let parsed: u128 = digits.parse().map_err(|_| ValueErrorKind::InvalidBytes)?;
+			parsed * scale / 10u128.pow(digits.len() as u32)
+		}
+	};
+
+	u64::try_from(whole + fraction).map_err(|_| ValueErrorKind::TooLarge)
+}
+
+/// What separates the elements of a list value.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Delimiter {
+	/// `aes128-ctr, aes256-ctr`. Whitespace around each element is ignored.
+	Comma,
+	/// `a b  c`. Any amount of whitespace separates elements.
+	Whitespace,
+	/// Any other character, which works like [Comma](Delimiter::Comma).
+	Char(char),
+}
+
+impl Delimiter {
+	fn matches(self, c: char) -> bool {
+		match self {
+			Delimiter::Comma => c == ',',
+			Delimiter::Whitespace => c.is_whitespace(),
+			Delimiter::Char(delimiter) => c == delimiter,
+		}
+	}
+}
+
+/// Split a value into its elements like a shell would split words.
+///
+/// Single quotes keep everything between them as it is, double quotes
+/// keep everything but a backslash before a `"` or `\`, and outside of
+/// quotes a backslash keeps the character after it. Quoted text can touch
+/// unquoted text to make one element, like `a"b c"`.
+pub(crate) fn split(s: &str, delimiter: Delimiter) -> Result<Vec<String>, ValueErrorKind> {
+	// Anything but whitespace ends an element at each delimiter, even if
+	// that leaves it empty
+	let separated = delimiter != Delimiter::Whitespace;
+
+	let mut elements = vec![];
+	let mut element = String::new();
+	// Whether anything, even empty quotes, has been put in the element
+	let mut started = false;
+	// How long the element is without unquoted whitespace at the end
+	let mut keep = 0;
+
+	let mut chars = s.chars();
+	while let Some(c) = chars.next() {
+		match c {
+			c if delimiter.matches(c) => {
+				if separated || started {
+					element.truncate(keep);
+					elements.push(std::mem::take(&mut element));
+					started = false;
+					keep = 0;
+				}
+				continue;
+			}
+			'\'' => loop {
+				match chars.next() {
+					None => return Err(ValueErrorKind::UnclosedQuote),
+					Some('\'') => break,
+					Some(c) => element.push(c),
+				}
+			},
+			'"' => loop {
+				match chars.next() {
+					None => return Err(ValueErrorKind::UnclosedQuote),
+					Some('"') => break,
+					Some('\\') => match chars.next() {
+						None => return Err(ValueErrorKind::UnclosedQuote),
+						Some(c @ ('"' | '\\')) => element.push(c),
+						Some(c) => {
+							element.push('\\');
+							element.push(c);
+						}
+					},
+					Some(c) => element.push(c),
+				}
+			},
+			'\\' => element.push(chars.next().ok_or(ValueErrorKind::TrailingBackslash)?),
+			c if c.is_whitespace() => {
+				// Leading whitespace is dropped and trailing is cut by keep
+				if started {
+					element.push(c);
+				}
+				continue;
+			}
+			c => element.push(c),
+		}
+
+		started = true;
+		keep = element.len();
+	}
+
+	if separated || started {
+		element.truncate(keep);
+		elements.push(element);
+	}
+
+	Ok(elements)
+}
+
+/// Digits as a u64, which are only ever wrong by being too long.
+fn number(digits: &str) -> Result<u64, ValueErrorKind> {
+	digits.parse().map_err(|_| ValueErrorKind::TooLarge)
+}
+
+fn split_at_first<F: Fn(char) -> bool>(s: &str, f: F) -> (&str, &str) {
+	s.split_at(s.find(f).unwrap_or(s.len()))
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use crate::Confindent;
+
+	#[test]
+	fn durations() {
+		let secs = |s| parse_duration(s).map(|d| d.as_millis());
+
+		assert_eq!(secs("30"), Ok(30_000));
+		assert_eq!(secs("30s"), Ok(30_000));
+		assert_eq!(secs("250ms"), Ok(250));
+		assert_eq!(secs("1h30m"), Ok(5_400_000));
+		assert_eq!(secs("1d 2h"), Ok(93_600_000));
+		assert_eq!(secs("5 m"), Ok(300_000));
+		assert_eq!(secs(""), Err(ValueErrorKind::InvalidDuration));
+		assert_eq!(secs("s"), Err(ValueErrorKind::InvalidDuration));
+		assert_eq!(secs("10x"), Err(ValueErrorKind::InvalidDuration));
+		assert_eq!(secs("1h30"), Err(ValueErrorKind::InvalidDuration));
+		assert_eq!(secs("99999999999999999999d"), Err(ValueErrorKind::TooLarge));
+	}
+
+	#[test]
+	fn byte_sizes() {
+		assert_eq!(parse_bytes("512"), Ok(512));
+		assert_eq!(parse_bytes("512B"), Ok(512));
+		assert_eq!(parse_bytes("64KB"), Ok(64_000));
+		assert_eq!(parse_bytes("64kib"), Ok(65_536));
+		assert_eq!(parse_bytes("10 MiB"), Ok(10 * 1024 * 1024));
+		assert_eq!(parse_bytes("1G"), Ok(1 << 30));
+		assert_eq!(parse_bytes("1.5KiB"), Ok(1536));
+		assert_eq!(parse_bytes("16EiB"), Err(ValueErrorKind::TooLarge));
+		assert_eq!(parse_bytes("MB"), Err(ValueErrorKind::InvalidBytes));
+		assert_eq!(parse_bytes("10 bits"), Err(ValueErrorKind::InvalidBytes));
+		assert_eq!(parse_bytes("1.2.3"), Err(ValueErrorKind::InvalidBytes));
+	}
+
+	#[test]
+	fn shell_splitting() {
+		let split = |s, d| split(s, d).unwrap();
+
+		assert_eq!(split("a  b\tc", Delimiter::Whitespace), ["a", "b", "c"]);
+		assert_eq!(
+			split(r#"'a b' "c \"d\"" e\ f"#, Delimiter::Whitespace),
+			["a b", "c \"d\"", "e f"]
+		);
+		assert_eq!(split(r#"x"y z"'w'"#, Delimiter::Whitespace), ["xy zw"]);
+		assert_eq!(split(r#""" a"#, Delimiter::Whitespace), ["", "a"]);
+		assert_eq!(
+			split(" aes128-ctr , 'a,b' ,,c ", Delimiter::Comma),
+			["aes128-ctr", "a,b", "", "c"]
+		);
+		assert_eq!(split("' x ' , y", Delimiter::Comma), [" x ", "y"]);
+		assert_eq!(split("a:b\\:c", Delimiter::Char(':')), ["a", "b:c"]);
+
+		let err = |s| super::split(s, Delimiter::Whitespace).unwrap_err();
+		assert_eq!(err("'open"), ValueErrorKind::UnclosedQuote);
+		assert_eq!(err("\"open\\\""), ValueErrorKind::UnclosedQuote);
+		assert_eq!(err("end\\"), ValueErrorKind::TrailingBackslash);
+	}
+
+	#[test]
+	fn short_type_names() {
+		assert_eq!(type_name::<u16>(), "u16");
+		assert_eq!(type_name::<String>(), "String");
+		assert_eq!(type_name::<Vec<std::net::IpAddr>>(), "Vec<IpAddr>");
+		assert_eq!(type_name::<(bool, &str)>(), "(bool, &str)");
+	}
+
+	#[test]
+	fn parse_errors_carry_context() {
+		let conf: Confindent = "Host\n\tPort\n\tAddress ::x".parse().unwrap();
+		let host = conf.child("Host").unwrap();
+
+		let missing = host.child_parse::<_, u16>("User").unwrap_err();
+		assert_eq!(missing.path, "User");
+		assert_eq!(missing.kind, ValueErrorKind::MissingKey);
+
+		let no_value = conf.get_parse::<_, u16>("Host/Port").unwrap_err();
+		assert_eq!(no_value.line, Some(1));
+		assert_eq!(no_value.kind, ValueErrorKind::MissingValue);
+
+		let invalid = host
+			.child_parse::<_, std::net::IpAddr>("Address")
+			.unwrap_err();
+		assert_eq!(
+			invalid,
+			ValueError {
+				path: "Address".into(),
+				line: Some(2),
+				raw: Some("::x".into()),
+				kind: ValueErrorKind::Invalid {
+					type_name: "IpAddr".into(),
+					message: "invalid IP address syntax".into()
+				}
+			}
+		);
+	}
+
+	#[test]
+	fn list_errors_say_which_element() {
+		let conf: Confindent = "Ports 22 x 80".parse().unwrap();
+		let err = conf
+			.child("Ports")
+			.unwrap()
+			.parse_list::<u16>(Delimiter::Whitespace)
+			.unwrap_err();
+
+		assert_eq!(err.path, "Ports");
+		assert_eq!(err.line, Some(0));
+		assert_eq!(
+			err.kind,
+			ValueErrorKind::InvalidElement {
+				index: 1,
+				element: "x".into(),
+				message: "invalid digit found in string".into()
+			}
+		);
+	}
+
+	#[test]
+	fn flags_and_context() {
+		let conf: Confindent = "Host\n\tCompression\n\tForward off\n\tTimeout 5 minutes"
+			.parse()
+			.unwrap();
+
+		assert_eq!(conf.flag("Host/Compression"), Ok(true));
+		assert_eq!(conf.flag("Host/Forward"), Ok(false));
+		assert_eq!(conf.flag("Host/Missing"), Ok(false));
+		assert_eq!(
+			conf.get_duration("Host/Timeout"),
+			Err(ValueError {
+				path: "Host/Timeout".into(),
+				line: Some(3),
+				raw: Some("5 minutes".into()),
+				kind: ValueErrorKind::InvalidDuration
+			})
+		);
+		assert_eq!(
+			conf.get_bool("Host/Compression").unwrap_err().kind,
+			ValueErrorKind::MissingValue
+		);
+		assert_eq!(
+			conf.get_bytes("Nope").unwrap_err().kind,
+			ValueErrorKind::MissingKey
+		);
+	}
+}
@@ -1,19 +1,37 @@
-use std::{fmt, str::FromStr};
+use std::{fmt, str::FromStr, time::Duration};
 
 use crate::{
-	error::{ParseErrorKind, ValueParseError},
+	error::{ParseErrorKind, ValueError, ValueErrorKind},
 	indent::Indent,
-	line::Line,
+	index::{Children, KeyCase, KeyIndex},
+	line::{Line, LineEnding},
+	secret::{Secret, REDACTED},
+	typed::{self, Delimiter},
 	ValueIterator, ValueIteratorMut,
 };
 
 /// A parsed line of a configuration file.
-#[derive(Clone, Debug, PartialEq)]
+#[derive(Clone)]
 pub struct Value {
 	pub(crate) indent: Indent,
+	/// The indent as it was written, if it mixed tabs and spaces so
+	/// `indent` can't write it. Moving the value forgets it.
+	pub(crate) written_indent: Option<String>,
 	pub(crate) key: String,
 	pub(crate) value: Option<String>,
+	/// What was between the key and value if it wasn't a single space, or if
+	/// there was a space but no value.
+	pub(crate) separator: Option<String>,
+	/// The ending of this line if it differs from the document's
+	pub(crate) ending: Option<LineEnding>,
+	/// The line this value was parsed from
+	pub(crate) line: Option<usize>,
 	pub(crate) children: Vec<Line>,
+	pub(crate) index: KeyIndex,
+	/// Whether the value is printed as `<redacted>`
+	pub(crate) secret: bool,
+	/// Whether the value was copied from another block by `Inherit`
+	pub(crate) inherited: bool,
 }
 
 impl Value {
@@ -29,7 +47,14 @@ impl Value {
 			indent,
 			key: key.into(),
 			value: if value.is_empty() { None } else { Some(value) },
+			separator: None,
+			ending: None,
+			line: None,
 			children: vec![],
+			index: KeyIndex::default(),
+			secret: false,
+			inherited: false,
+			written_indent: None,
 		}
 	}
 
@@ -38,7 +63,14 @@ impl Value {
 			indent: Indent::Empty,
 			key: key.into(),
 			value: value.map(|v| v.to_string()),
+			separator: None,
+			ending: None,
+			line: None,
 			children: vec![],
+			index: KeyIndex::default(),
+			secret: false,
+			inherited: false,
+			written_indent: None,
 		}
 	}
 
@@ -46,28 +78,32 @@ impl Value {
 		self.get_delim(path, '/')
 	}
 
-	pub fn get_parse<S: AsRef<str>, T: FromStr>(&self, path: S) -> Result<T, ValueParseError<T>> {
+	pub fn get_parse<S: AsRef<str>, T>(&self, path: S) -> Result<T, ValueError>
+	where
+		T: FromStr,
+		T::Err: fmt::Display,
+	{
 		self.get_delim_parse(path, '/')
 	}
 
-	pub fn get_delim_parse<S: AsRef<str>, T: FromStr>(
+	pub fn get_delim_parse<S: AsRef<str>, T>(
 		&self,
 		path: S,
 		delimeter: char,
-	) -> Result<T, ValueParseError<T>> {
-		self.get_delim(path, delimeter)
-			.map(|child| child.parse().map_err(|e| ValueParseError::ParseError(e)))
-			.unwrap_or(Err(ValueParseError::NoValue))
+	) -> Result<T, ValueError>
+	where
+		T: FromStr,
+		T::Err: fmt::Display,
+	{
+		let path = path.as_ref();
+		typed::typed(self.walk(path, delimeter), path, typed::from_str)
 	}
 
 	pub fn get_delim<S: AsRef<str>>(&self, path: S, delimeter: char) -> Option<&str> {
 		let path = path.as_ref();
 		let mut splits = path.split(delimeter);
 
-		let mut current = match splits.next().and_then(|key| self.child(key)) {
-			None => return None,
-			Some(child) => child,
-		};
+		let mut current = splits.next().and_then(|key| self.child(key))?;
 
 		for key in splits {
 			match current.child(key) {
@@ -80,14 +116,14 @@ impl Value {
 	}
 
 	//TODO: docs
-	pub fn values(&self) -> ValueIterator {
+	pub fn values(&self) -> ValueIterator<'_> {
 		ValueIterator {
 			inner: self.children.iter(),
 		}
 	}
 
 	//TODO: docs
-	pub fn values_mut(&mut self) -> ValueIteratorMut {
+	pub fn values_mut(&mut self) -> ValueIteratorMut<'_> {
 		ValueIteratorMut {
 			inner: self.children.iter_mut(),
 		}
@@ -113,7 +149,7 @@ impl Value {
 		loop {
 			match iter.next() {
 				Some((_, c)) if c.is_ascii_whitespace() => continue,
-				Some((i, c)) if !c.is_ascii_whitespace() => return i,
+				Some((i, _)) => return i,
 				_ => return 0,
 			}
 		}
@@ -124,24 +160,34 @@ impl Value {
 		Ok((whitespace.parse()?, other))
 	}
 
-	pub(crate) fn from_indent_str(indent: Indent, line: &str) -> Self {
-		let (key, value) = match line.split_once(' ') {
-			None => (line.to_owned(), None),
-			Some((key, value)) if value.is_empty() => (key.to_owned(), None),
-			Some((key, value)) => (key.to_owned(), Some(value.to_owned())),
-		};
-
-		Self {
-			indent,
-			key,
-			value,
-			children: vec![],
+	/// Split a line, without its indent, into the key, the value, and the
+	/// separator if it has to be remembered.
+	pub(crate) fn split_key_value(line: &str) -> (&str, Option<&str>, Option<&str>) {
+		match line.split_once(' ') {
+			None => (line, None, None),
+			Some((key, "")) => (key, None, Some(" ")),
+			Some((key, value)) => (key, Some(value), None),
 		}
 	}
 
+	#[allow(dead_code)] //used in tests
 	pub(crate) fn from_str(line: &str) -> Result<Self, ParseErrorKind> {
-		let (white, expr) = Self::split_whitespace(line)?;
-		Ok(Value::from_indent_str(white, expr))
+		let (indent, expr) = Self::split_whitespace(line)?;
+		let (key, value, separator) = Self::split_key_value(expr);
+
+		Ok(Self {
+			indent,
+			key: key.to_owned(),
+			value: value.map(<_>::to_owned),
+			separator: separator.map(<_>::to_owned),
+			ending: None,
+			line: None,
+			children: vec![],
+			index: KeyIndex::default(),
+			secret: false,
+			inherited: false,
+			written_indent: None,
+		})
 	}
 
 	/// Get the first child with the provided key
@@ -159,12 +205,12 @@ impl Value {
 	/// assert_eq!(grandchild.value(), Some("value"));
 	/// ```
 	pub fn child<S: AsRef<str>>(&self, key: S) -> Option<&Value> {
-		self.values().find(|value| value.key == key.as_ref())
+		self.children_iter(key).next()
 	}
 
 	//TODO: docs
 	pub fn child_mut<S: AsRef<str>>(&mut self, key: S) -> Option<&mut Value> {
-		self.values_mut().find(|value| value.key == key.as_ref())
+		child_mut(&mut self.children, &self.index, key.as_ref())
 	}
 
 	/// Get every child that is a direct descendant of this value with the provided name.
@@ -184,15 +230,39 @@ impl Value {
 	/// assert_eq!(children[1].value(), Some("morevalue"));
 	/// ```
 	pub fn children<S: AsRef<str>>(&self, key: S) -> Vec<&Value> {
-		self.values()
-			.filter(|value| value.key == key.as_ref())
-			.collect()
+		self.children_iter(key).collect()
+	}
+
+	/// Like [children](Value::children), but without collecting them into a `Vec`.
+	///
+	/// Values with a lot of children keep track of where each key is, so
+	/// this doesn't have to look at all of them.
+	///
+	/// # Example
+	///
+	/// ```rust
+	/// use confindent::Confindent;
+	///
+	/// let confstr = "Host example.com\n\tAlias one\n\tPort 22\n\tAlias two";
+	///
+	/// let conf: Confindent = confstr.parse().unwrap();
+	/// let host = conf.child("Host").unwrap();
+	/// let aliases: Vec<&str> = host
+	/// 	.children_iter("Alias")
+	/// 	.filter_map(|alias| alias.value())
+	/// 	.collect();
+	///
+	/// assert_eq!(aliases, vec!["one", "two"]);
+	/// ```
+	pub fn children_iter<S: AsRef<str>>(&self, key: S) -> Children<'_, S> {
+		Children::new(&self.children, &self.index, key)
 	}
 
 	//TODO: docs
 	pub fn children_mut<S: AsRef<str>>(&mut self, key: S) -> Vec<&mut Value> {
+		let case = self.index.case();
 		self.values_mut()
-			.filter(|value| value.key == key.as_ref())
+			.filter(|value| case.matches(&value.key, key.as_ref()))
 			.collect()
 	}
 
@@ -215,7 +285,7 @@ impl Value {
 	/// assert!(host.has_child("UseCompression"));
 	/// ```
 	pub fn has_child<S: AsRef<str>>(&self, key: S) -> bool {
-		self.values().any(|value| value.key == key.as_ref())
+		self.children_iter(key).next().is_some()
 	}
 
 	/// Get the value of the first child with the provided key.
@@ -263,6 +333,44 @@ impl Value {
 		self.child_value(key).map(<_>::to_owned)
 	}
 
+	/// The key of this value.
+	pub fn key(&self) -> &str {
+		&self.key
+	}
+
+	/// How this value compares keys when looking up its children.
+	pub fn key_case(&self) -> KeyCase {
+		self.index.case()
+	}
+
+	/// Compare keys the provided way when looking up children of this value,
+	/// and of all of its children. Children added later compare keys the
+	/// same way. See [KeyCase] for an example.
+	pub fn set_key_case(&mut self, case: KeyCase) {
+		self.index.set_case(case);
+		for child in self.values_mut() {
+			child.set_key_case(case);
+		}
+	}
+
+	/// The line this value was parsed from, starting from zero like the
+	/// lines in [ParseError](crate::ParseError). Values you made yourself
+	/// don't have one.
+	///
+	/// # Example
+	///
+	/// ```rust
+	/// use confindent::Confindent;
+	///
+	/// let conf: Confindent = "Host example.com\n\n\tPort 22".parse().unwrap();
+	/// let port = conf.child("Host").unwrap().child("Port").unwrap();
+	///
+	/// assert_eq!(port.line(), Some(2));
+	/// ```
+	pub fn line(&self) -> Option<usize> {
+		self.line
+	}
+
 	/// Gets the contained value.
 	///
 	/// # Returns
@@ -322,10 +430,13 @@ impl Value {
 	///
 	/// assert_eq!(host.child_parse("Port"), Ok(22));
 	/// ```
-	pub fn child_parse<S: AsRef<str>, T: FromStr>(&self, key: S) -> Result<T, ValueParseError<T>> {
-		self.child(key)
-			.map(|child| child.parse())
-			.unwrap_or(Err(ValueParseError::NoValue))
+	pub fn child_parse<S: AsRef<str>, T>(&self, key: S) -> Result<T, ValueError>
+	where
+		T: FromStr,
+		T::Err: fmt::Display,
+	{
+		let key = key.as_ref();
+		typed::typed(self.child(key), key, typed::from_str)
 	}
 
 	/// The same as [value](Value::value) but parses the value into your type. The type you're trying to
@@ -343,41 +454,607 @@ impl Value {
 	///
 	/// assert_eq!(conf.child("Port").unwrap().parse(), Ok(22));
 	/// ```
-	pub fn parse<T: FromStr>(&self) -> Result<T, ValueParseError<T>> {
-		self.value
-			.as_ref()
-			.map(|child| child.parse().map_err(|e| ValueParseError::ParseError(e)))
-			.unwrap_or(Err(ValueParseError::NoValue))
+	pub fn parse<T>(&self) -> Result<T, ValueError>
+	where
+		T: FromStr,
+		T::Err: fmt::Display,
+	{
+		typed::typed(Some(self), &self.key, typed::from_str)
 	}
 
-	pub fn parse_opt<T: FromStr>(&self) -> Option<Result<T, ValueParseError<T>>> {
-		self.value
-			.as_ref()
-			.map(|child| child.parse().map_err(|e| ValueParseError::ParseError(e)))
+	/// Like [parse](Value::parse), but a missing value is `None` instead of
+	/// an error.
+	pub fn parse_opt<T>(&self) -> Option<Result<T, ValueError>>
+	where
+		T: FromStr,
+		T::Err: fmt::Display,
+	{
+		self.value.as_ref().map(|_| self.parse())
 	}
-}
 
-impl fmt::Display for Value {
-	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+	/// Parse the value as a bool, accepting `yes`/`no`, `on`/`off`,
+	/// `true`/`false`, and `1`/`0` in any case.
+	///
+	/// # Example
+	///
+	/// ```rust
+	/// use confindent::Confindent;
+	///
+	/// let conf: Confindent = "UseCompression Yes".parse().unwrap();
+	///
+	/// assert_eq!(conf.child("UseCompression").unwrap().parse_bool(), Ok(true));
+	/// ```
+	pub fn parse_bool(&self) -> Result<bool, ValueError> {
+		typed::typed(Some(self), &self.key, typed::parse_bool)
+	}
+
+	/// Parse the value as a duration made of numbers and units, `ms`, `s`,
+	/// `m`, `h`, and `d`, like `1h30m` or `250ms`. A number alone is seconds.
+	///
+	/// # Example
+	///
+	/// ```rust
+	/// use confindent::Confindent;
+	/// use std::time::Duration;
+	///
+	/// let conf: Confindent = "Timeout 1m 30s".parse().unwrap();
+	/// let timeout = conf.child("Timeout").unwrap().parse_duration();
+	///
+	/// assert_eq!(timeout, Ok(Duration::from_secs(90)));
+	/// ```
+	pub fn parse_duration(&self) -> Result<Duration, ValueError> {
+		typed::typed(Some(self), &self.key, typed::parse_duration)
+	}
+
+	/// Parse the value as a number of bytes, like `512`, `64KB`, or `1.5GiB`.
+	///
+	/// Units like `KB` and `MB` are powers of 1000, and units like `KiB`
+	/// and `MiB`, or just `K` and `M`, are powers of 1024. Case doesn't
+	/// matter.
+	///
+	/// # Example
+	///
+	/// ```rust
+	/// use confindent::Confindent;
+	///
+	/// let conf: Confindent = "MaxSize 10MiB".parse().unwrap();
+	///
+	/// assert_eq!(conf.child("MaxSize").unwrap().parse_bytes(), Ok(10 * 1024 * 1024));
+	/// ```
+	pub fn parse_bytes(&self) -> Result<u64, ValueError> {
+		typed::typed(Some(self), &self.key, typed::parse_bytes)
+	}
+
+	/// Split the value into a list and parse each element into your type.
+	///
+	/// Elements can be quoted, or have characters escaped with a backslash,
+	/// like they can in a shell. A key without a value is an empty list.
+	/// If an element can't be parsed, the error says which one.
+	///
+	/// # Example
+	///
+	/// ```rust
+	/// use confindent::{Confindent, Delimiter};
+	///
+	/// let conf: Confindent = "Ports 22, 2222,'8022'".parse().unwrap();
+	/// let ports = conf.child("Ports").unwrap().parse_list(Delimiter::Comma);
+	///
+	/// assert_eq!(ports, Ok(vec![22u16, 2222, 8022]));
+	/// ```
+	pub fn parse_list<T>(&self, delimiter: Delimiter) -> Result<Vec<T>, ValueError>
+	where
+		T: FromStr,
+		T::Err: fmt::Display,
+	{
+		let raw = match self.value() {
+			None => return Ok(vec![]),
+			Some(raw) => raw,
+		};
+		let shown = if self.secret { REDACTED } else { raw };
+		let error = |kind| ValueError {
+			path: self.key.clone(),
+			line: self.line,
+			raw: Some(shown.to_owned()),
+			kind,
+		};
+
+		let elements = typed::split(raw, delimiter).map_err(error)?;
+		elements
+			.into_iter()
+			.enumerate()
+			.map(|(index, element)| {
+				element.parse().map_err(|e: T::Err| {
+					error(ValueErrorKind::InvalidElement {
+						index,
+						message: e.to_string(),
+						element: match self.secret {
+							true => REDACTED.to_owned(),
+							false => element,
+						},
+					})
+				})
+			})
+			.collect()
+	}
+
+	/// Split the value into words like a shell would.
+	///
+	/// See [parse_list](Value::parse_list) for more.
+	///
+	/// # Example
+	///
+	/// ```rust
+	/// use confindent::Confindent;
+	///
+	/// let conf: Confindent = r#"IdentityFile ~/.ssh/id "~/My Keys/id" a\ b"#.parse().unwrap();
+	/// let files = conf.child("IdentityFile").unwrap().words().unwrap();
+	///
+	/// assert_eq!(files, ["~/.ssh/id", "~/My Keys/id", "a b"]);
+	/// ```
+	pub fn words(&self) -> Result<Vec<String>, ValueError> {
+		self.parse_list(Delimiter::Whitespace)
+	}
+
+	/// Whether a flag at a `/` separated path of keys is set.
+	///
+	/// A key without a value is set, a key that isn't there isn't, and a
+	/// key with a value is whatever [parse_bool](Value::parse_bool) says.
+	///
+	/// # Example
+	///
+	/// ```rust
+	/// use confindent::Confindent;
+	///
+	/// let conf: Confindent = "Host\n\tCompression\n\tAgent no".parse().unwrap();
+	/// let host = conf.child("Host").unwrap();
+	///
+	/// assert_eq!(host.flag("Compression"), Ok(true));
+	/// assert_eq!(host.flag("Agent"), Ok(false));
+	/// assert_eq!(host.flag("X11"), Ok(false));
+	/// ```
+	pub fn flag<S: AsRef<str>>(&self, path: S) -> Result<bool, ValueError> {
+		let path = path.as_ref();
+		typed::flag(self.walk(path, '/'), path)
+	}
+
+	/// Get a value from a `/` separated path of keys and parse it with
+	/// [parse_bool](Value::parse_bool).
+	pub fn get_bool<S: AsRef<str>>(&self, path: S) -> Result<bool, ValueError> {
+		let path = path.as_ref();
+		typed::typed(self.walk(path, '/'), path, typed::parse_bool)
+	}
+
+	/// Get a value from a `/` separated path of keys and parse it with
+	/// [parse_duration](Value::parse_duration).
+	pub fn get_duration<S: AsRef<str>>(&self, path: S) -> Result<Duration, ValueError> {
+		let path = path.as_ref();
+		typed::typed(self.walk(path, '/'), path, typed::parse_duration)
+	}
+
+	/// Get a value from a `/` separated path of keys and parse it with
+	/// [parse_bytes](Value::parse_bytes).
+	pub fn get_bytes<S: AsRef<str>>(&self, path: S) -> Result<u64, ValueError> {
+		let path = path.as_ref();
+		typed::typed(self.walk(path, '/'), path, typed::parse_bytes)
+	}
+
+	fn walk(&self, path: &str, delimiter: char) -> Option<&Value> {
+		typed::walk(&self.children, &self.index, path, delimiter)
+	}
+
+	/// Whether the value is printed as `<redacted>`. See
+	/// [Confindent::mark_secret](crate::Confindent::mark_secret).
+	pub fn is_secret(&self) -> bool {
+		self.secret
+	}
+
+	/// Print the value as `<redacted>`, or stop doing so. The value can
+	/// still be read, and it's still saved.
+	pub fn set_secret(&mut self, secret: bool) {
+		self.secret = secret;
+	}
+
+	/// The value as a [Secret], whether or not it's been marked as one.
+	pub fn secret(&self) -> Option<Secret> {
+		self.value.as_deref().map(Secret::new)
+	}
+
+	/// Set, or clear, the contained value.
+	///
+	/// The value is separated from its key however it was before. Clearing
+	/// it clears the separator too, so nothing's left after the key.
+	///
+	/// # Example
+	///
+	/// ```rust
+	/// use confindent::Confindent;
+	///
+	/// let mut conf: Confindent = "Port 22".parse().unwrap();
+	/// conf.child_mut("Port").unwrap().set_value(Some(2222));
+	///
+	/// assert_eq!(conf.to_string(), "Port 2222");
+	/// ```
+	pub fn set_value<V: fmt::Display>(&mut self, value: Option<V>) {
+		self.value = value.map(|v| v.to_string());
+		if self.value.is_none() {
+			self.separator = None;
+		}
+	}
+
+	/// Set the value, and if it didn't have one before, separate it from its
+	/// key with `separator` like the values around it.
+	pub(crate) fn set_value_like<V: fmt::Display>(
+		&mut self,
+		value: Option<V>,
+		separator: Option<String>,
+	) {
+		let had_value = self.value.is_some();
+		self.set_value(value);
+
+		if !had_value && self.value.is_some() && self.separator.is_none() {
+			self.separator = separator;
+		}
+	}
+
+	/// Add a child to the end of this value's children.
+	///
+	/// The child, and any children it has, are indented to match the
+	/// existing children. If there aren't any, it's indented one level
+	/// deeper than this value. Its lines end like the line before them,
+	/// and it's separated from its key like its siblings are.
+	///
+	/// # Example
+	///
+	/// ```rust
+	/// use confindent::{Confindent, Value};
+	///
+	/// let mut conf: Confindent = "Host example.com\r\n\tPort 22\r\n".parse().unwrap();
+	/// let host = conf.child_mut("Host").unwrap();
+	/// host.add_child(Value::new("User", Some("gen")));
+	///
+	/// assert_eq!(
+	/// 	conf.to_string(),
+	/// 	"Host example.com\r\n\tPort 22\r\n\tUser gen\r\n"
+	/// );
+	/// ```
+	pub fn add_child(&mut self, value: Value) {
+		self.push_line(Line::Value(value));
+	}
+
+	/// Add a comment after the children of this value, indented like them.
+	/// The comment is written after the `#` as it is, so start it with a
+	/// space if you'd like one there.
+	///
+	/// # Example
+	///
+	/// ```rust
+	/// use confindent::{Confindent, Value};
+	///
+	/// let mut conf: Confindent = "Host example.com\n\tPort 22\n".parse().unwrap();
+	/// let host = conf.child_mut("Host").unwrap();
+	/// host.add_comment(" the default user");
+	/// host.add_child(Value::new("User", Some("gen")));
+	///
+	/// assert_eq!(
+	/// 	conf.to_string(),
+	/// 	"Host example.com\n\tPort 22\n\t# the default user\n\tUser gen\n"
+	/// );
+	/// ```
+	pub fn add_comment<S: Into<String>>(&mut self, comment: S) {
+		self.push_line(Line::from((Indent::Empty, comment.into())));
+	}
+
+	/// Add a line after the children, indenting it like the ones already
+	/// there or one deeper than us if there aren't any. Values are separated
+	/// from their keys like the ones already there, too.
+	pub(crate) fn push_line(&mut self, mut line: Line) {
+		// The line goes right after the last one written here, so it ends
+		// like that one does
+		let ending = self.children.last().map_or(self.ending, Line::last_ending);
+		line.set_ending(ending);
+
+		let child = self
+			.children
+			.iter()
+			.find_map(|line| match line {
+				Line::Value(v) => Some(v.indent),
+				Line::Comment { indent, .. } => Some(*indent),
+				Line::Blank { .. } => None,
+			})
+			.unwrap_or_else(|| self.indent.child());
+
+		line.reindent(child);
+		if let Line::Value(value) = &mut line {
+			value.set_key_case(self.index.case());
+			if let Some(separator) = separator_of(&self.children) {
+				value.separate_with(separator);
+			}
+			self.index.pushed(&value.key, self.children.len());
+		}
+		self.children.push(line);
+	}
+
+	/// Set the value at a `/` separated path of keys, adding any keys that
+	/// aren't there yet. Returns the value that was set.
+	///
+	/// Printing the document afterwards only changes the line that was set
+	/// and any that were added. Those look like the lines around them.
+	///
+	/// # Example
+	///
+	/// ```rust
+	/// use confindent::Confindent;
+	///
+	/// let mut conf: Confindent = "Host example.com\n\tPort 22\n".parse().unwrap();
+	/// let host = conf.child_mut("Host").unwrap();
+	/// host.set("Port", Some(2222));
+	/// host.set("Proxy/Jump", Some("bastion"));
+	///
+	/// assert_eq!(
+	/// 	conf.to_string(),
+	/// 	"Host example.com\n\tPort 2222\n\tProxy\n\t\tJump bastion\n"
+	/// );
+	/// ```
+	pub fn set<S: AsRef<str>, V: fmt::Display>(&mut self, path: S, value: Option<V>) -> &mut Value {
+		self.set_styled(path.as_ref(), value, None)
+	}
+
+	/// Like [set](Value::set), with `separator` separating values from their
+	/// keys around this one. Deeper values that have siblings to go by are
+	/// separated like them instead.
+	pub(crate) fn set_styled<V: fmt::Display>(
+		&mut self,
+		path: &str,
+		value: Option<V>,
+		mut separator: Option<String>,
+	) -> &mut Value {
+		let mut current = self;
+		let mut keys = path.split('/');
+		let mut key = keys.next().unwrap_or_default();
+
+		for next in keys {
+			if let Some(found) = first_separator(&current.children) {
+				separator = found.map(<_>::to_owned);
+			}
+			current = current.child_or_add(key);
+			key = next;
+		}
+
+		if let Some(found) = first_separator(&current.children) {
+			separator = found.map(<_>::to_owned);
+		}
+		let current = current.child_or_add(key);
+		current.set_value_like(value, separator);
+		current
+	}
+
+	/// Remove the first value at a `/` separated path of keys, and return
+	/// it if there was one. Its children go with it.
+	///
+	/// # Example
+	///
+	/// ```rust
+	/// use confindent::Confindent;
+	///
+	/// let mut conf: Confindent = "Host example.com\n\tPort 22\n\tUser gen\n".parse().unwrap();
+	/// let port = conf.child_mut("Host").unwrap().remove("Port");
+	///
+	/// assert_eq!(port.unwrap().value(), Some("22"));
+	/// assert_eq!(conf.to_string(), "Host example.com\n\tUser gen\n");
+	/// ```
+	pub fn remove<S: AsRef<str>>(&mut self, path: S) -> Option<Value> {
+		let (parent, key) = match path.as_ref().rsplit_once('/') {
+			None => (self, path.as_ref()),
+			Some((parent, key)) => (self.walk_mut(parent)?, key),
+		};
+
+		remove_child(&mut parent.children, &mut parent.index, key)
+	}
+
+	/// The first child with the key, after adding it if there wasn't one.
+	pub(crate) fn child_or_add(&mut self, key: &str) -> &mut Value {
+		if !self.has_child(key) {
+			self.add_child(Value::new(key, None::<&str>));
+		}
+
+		child_mut(&mut self.children, &self.index, key).unwrap()
+	}
+
+	pub(crate) fn walk_mut(&mut self, path: &str) -> Option<&mut Value> {
+		let mut current = self;
+		for key in path.split('/') {
+			current = current.child_mut(key)?;
+		}
+
+		Some(current)
+	}
+
+	/// Indent this value's children with tabs, one per level, and take any
+	/// whitespace off of blank lines.
+	pub(crate) fn normalize(&mut self, indent: Indent) {
+		self.reindent(indent);
+		normalize_blanks(&mut self.children);
+	}
+
+	/// Separate this value, and its children, from their keys with the
+	/// separator, unless they already have their own.
+	pub(crate) fn separate_with(&mut self, separator: &str) {
+		if self.separator.is_none() && self.value.is_some() {
+			self.separator = Some(separator.to_owned());
+		}
+
+		for child in self.values_mut() {
+			child.separate_with(separator);
+		}
+	}
+
+	/// Move this value, and its children, to the provided indent.
+	pub(crate) fn reindent(&mut self, indent: Indent) {
+		self.indent = indent;
+		self.written_indent = None;
+
+		let child_indent = indent.child();
+		for child in self.children.iter_mut() {
+			child.reindent(child_indent);
+		}
+	}
+
+	/// Write this value and its children, using `ending` for lines that
+	/// don't have their own, and leaving out secret values if `redact`.
+	pub(crate) fn write_to<W: fmt::Write>(
+		&self,
+		w: &mut W,
+		ending: LineEnding,
+		redact: bool,
+	) -> fmt::Result {
 		let Value {
 			indent,
+			written_indent,
 			key,
 			value,
+			separator,
+			ending: own_ending,
 			children,
+			..
 		} = self;
 
-		if let Some(value) = value {
-			writeln!(f, "{indent}{key} {value}")?;
-		} else {
-			writeln!(f, "{indent}{key}")?;
+		let separator = match (separator, value) {
+			(Some(separator), _) => separator.as_str(),
+			(None, Some(_)) => " ",
+			(None, None) => "",
+		};
+		let value = match value {
+			Some(_) if redact && self.secret => REDACTED,
+			value => value.as_deref().unwrap_or_default(),
+		};
+		let own_ending = own_ending.unwrap_or(ending);
+
+		match written_indent {
+			Some(written) => w.write_str(written)?,
+			None => write!(w, "{indent}")?,
 		}
+		write!(w, "{key}{separator}{value}{own_ending}")?;
 
 		for child in children {
-			write!(f, "{child}")?;
+			child.write_to(w, ending, redact)?;
 		}
 
 		Ok(())
 	}
+
+	/// Give this value, and its children, their own line ending. None means
+	/// the document's.
+	pub(crate) fn set_endings(&mut self, ending: Option<LineEnding>) {
+		self.ending = ending;
+		for child in self.children.iter_mut() {
+			child.set_ending(ending);
+		}
+	}
+}
+
+/// How the first value in the lines that has a value is separated from its
+/// key, if it isn't with a single space.
+pub(crate) fn separator_of(lines: &[Line]) -> Option<&str> {
+	first_separator(lines)?
+}
+
+/// Like [separator_of], but None if no value in the lines has a value, and
+/// Some(None) if the first one is separated with a single space.
+pub(crate) fn first_separator(lines: &[Line]) -> Option<Option<&str>> {
+	lines.iter().find_map(|line| match line {
+		Line::Value(value) if value.value.is_some() => Some(value.separator.as_deref()),
+		_ => None,
+	})
+}
+
+/// Get the first value with the key from the lines, using the index if there is one.
+pub(crate) fn child_mut<'a>(
+	lines: &'a mut [Line],
+	index: &KeyIndex,
+	key: &str,
+) -> Option<&'a mut Value> {
+	match index.get(lines, key) {
+		Some(positions) => match positions.first().map(|idx| &mut lines[*idx]) {
+			Some(Line::Value(value)) => Some(value),
+			_ => None,
+		},
+		None => lines.iter_mut().find_map(|line| match line {
+			Line::Value(value) if index.matches(value, key) => Some(value),
+			_ => None,
+		}),
+	}
+}
+
+/// Remove the first value with the key from the lines.
+pub(crate) fn remove_child(
+	lines: &mut Vec<Line>,
+	index: &mut KeyIndex,
+	key: &str,
+) -> Option<Value> {
+	let position = lines
+		.iter()
+		.position(|line| matches!(line, Line::Value(value) if index.matches(value, key)))?;
+
+	index.clear();
+	match lines.remove(position) {
+		Line::Value(value) => Some(value),
+		_ => unreachable!(),
+	}
+}
+
+pub(crate) fn normalize_blanks(lines: &mut [Line]) {
+	for line in lines {
+		match line {
+			Line::Value(value) => normalize_blanks(&mut value.children),
+			Line::Blank { whitespace, .. } => whitespace.clear(),
+			Line::Comment { .. } => (),
+		}
+	}
+}
+
+// Where a value came from doesn't change what it is, so the line isn't
+// compared. Neither is whether it's secret, since that's only how it's shown,
+// or whether it was inherited.
+impl PartialEq for Value {
+	fn eq(&self, other: &Self) -> bool {
+		self.indent == other.indent
+			&& self.written_indent == other.written_indent
+			&& self.key == other.key
+			&& self.value == other.value
+			&& self.separator == other.separator
+			&& self.ending == other.ending
+			&& self.children == other.children
+	}
+}
+
+impl fmt::Display for Value {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		self.write_to(f, LineEnding::Lf, true)
+	}
+}
+
+// Like the derived Debug, but without the value if it's secret
+impl fmt::Debug for Value {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		let value = match &self.value {
+			Some(_) if self.secret => Some(REDACTED),
+			value => value.as_deref(),
+		};
+
+		f.debug_struct("Value")
+			.field("indent", &self.indent)
+			.field("written_indent", &self.written_indent)
+			.field("key", &self.key)
+			.field("value", &value)
+			.field("separator", &self.separator)
+			.field("ending", &self.ending)
+			.field("line", &self.line)
+			.field("children", &self.children)
+			.field("index", &self.index)
+			.field("secret", &self.secret)
+			.field("inherited", &self.inherited)
+			.finish()
+	}
 }
 
 #[cfg(test)]
@@ -454,6 +1131,13 @@ mod test {
 			indent: Indent::Empty,
 			key: "Key".into(),
 			value: Some("Value".into()),
+			separator: None,
+			ending: None,
+			line: None,
+			index: KeyIndex::default(),
+			secret: false,
+			inherited: false,
+			written_indent: None,
 			children: vec![Line::Value(Value::from_parts(
 				Indent::Tabs { count: 1, delta: 1 },
 				"ChildKey",
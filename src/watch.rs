@@ -0,0 +1,322 @@
+//! Reloading a document when its file changes.
+
+use std::{
+	fs,
+	path::{Path, PathBuf},
+	sync::{
+		atomic::{AtomicBool, Ordering},
+		Arc, Mutex,
+	},
+	thread::{self, JoinHandle},
+	time::{Duration, SystemTime},
+};
+
+use crate::{error::ParseError, line::Line, Confindent, ParseOptions, Value};
+
+/// Keeps a document up to date with the file it was read from, for
+/// programs that run long enough for it to change.
+///
+/// The file is checked whenever you call [check](Watcher::check), or every
+/// so often on another thread with [spawn](Watcher::spawn). When it's
+/// changed it's read again, and if that works the new document replaces
+/// the old one and everyone who [subscribed](Watcher::subscribe) hears
+/// about it. If it doesn't, they hear about the error instead and the last
+/// document that parsed stays [current](Watcher::current).
+///
+/// Changes are noticed by the file's modification time and length, so
+/// there are no dependencies and it works everywhere, including with
+/// editors that save by replacing the file.
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use std::time::Duration;
+/// use confindent::{Reload, Watcher};
+///
+/// let mut watcher = Watcher::new("daemon.conf").unwrap();
+/// watcher.subscribe(|reload| match reload {
+/// 	Reload::Changed { paths, .. } => println!("changed: {}", paths.join(", ")),
+/// 	Reload::Failed(e) => eprintln!("kept the old config: {e}"),
+/// });
+///
+/// let watching = watcher.spawn(Duration::from_secs(2));
+/// let port = watching.current().get_parse::<_, u16>("Server/Port");
+/// ```
+pub struct Watcher {
+	path: PathBuf,
+	options: ParseOptions,
+	current: Arc<Mutex<Arc<Confindent>>>,
+	/// When the file was last modified, and how long it was, the last time
+	/// we looked. None if we couldn't tell.
+	stamp: Option<(SystemTime, u64)>,
+	subscribers: Vec<Subscriber>,
+}
+
+type Subscriber = Box<dyn FnMut(&Reload) + Send>;
+
+/// What happened when a [Watcher] found that its file changed.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Reload {
+	/// The file was read again and is now the current document.
+	Changed {
+		conf: Arc<Confindent>,
+		/// The `/` separated path of every value that was added, removed,
+		/// or had its value changed, like [Confindent::get] takes. Values
+		/// under one that was added or removed are in here too.
+		paths: Vec<String>,
+	},
+	/// The file couldn't be read or parsed, so the document didn't change.
+	Failed(ParseError),
+}
+
+impl Watcher {
+	/// Read the file at `path` and start watching it.
+	pub fn new<P: AsRef<Path>>(path: P) -> Result<Self, ParseError> {
+		Self::with_options(path, &ParseOptions::default())
+	}
+
+	/// Like [new](Watcher::new), but reading the file the way the options
+	/// say to every time. See [ParseOptions].
+	pub fn with_options<P: AsRef<Path>>(
+		path: P,
+		options: &ParseOptions,
+	) -> Result<Self, ParseError> {
+		let path = path.as_ref().to_owned();
+		let stamp = stamp(&path);
+		let conf = Confindent::from_file_with(&path, options)?;
+
+		Ok(Self {
+			path,
+			options: options.clone(),
+			current: Arc::new(Mutex::new(Arc::new(conf))),
+			stamp,
+			subscribers: vec![],
+		})
+	}
+
+	/// The most recent document that parsed.
+	pub fn current(&self) -> Arc<Confindent> {
+		current(&self.current)
+	}
+
+	/// Call `f` every time the file changes, with the new document or the
+	/// reason there isn't one.
+	pub fn subscribe<F: FnMut(&Reload) + Send + 'static>(&mut self, f: F) {
+		self.subscribers.push(Box::new(f));
+	}
+
+	/// Look at the file, and read it again if it changed. Returns what
+	/// happened, after telling the subscribers, or None if nothing did.
+	///
+	/// A file that changed but parses to the same document, because only
+	/// its modification time did, isn't a change.
+	pub fn check(&mut self) -> Option<Reload> {
+		let stamp = stamp(&self.path);
+		if stamp == self.stamp {
+			return None;
+		}
+		self.stamp = stamp;
+
+		let reload = match Confindent::from_file_with(&self.path, &self.options) {
+			Err(e) => Reload::Failed(e),
+			Ok(conf) => {
+				let mut current = self.current.lock().unwrap_or_else(|e| e.into_inner());
+				if **current == conf {
+					return None;
+				}
+
+				let mut paths = vec![];
+				changed(&current.children, &conf.children, "", &mut paths);
+
+				let conf = Arc::new(conf);
+				*current = Arc::clone(&conf);
+				Reload::Changed { conf, paths }
+			}
+		};
+
+		for subscriber in &mut self.subscribers {
+			subscriber(&reload);
+		}
+		Some(reload)
+	}
+
+	/// Check the file every `interval` on another thread until it's
+	/// [stopped](Watching::stop).
+	pub fn spawn(mut self, interval: Duration) -> Watching {
+		let stop = Arc::new(AtomicBool::new(false));
+		let current = Arc::clone(&self.current);
+
+		let stopped = Arc::clone(&stop);
+		let thread = thread::spawn(move || {
+			while !stopped.load(Ordering::Relaxed) {
+				thread::park_timeout(interval);
+				self.check();
+			}
+			self
+		});
+
+		Watching {
+			current,
+			stop,
+			thread,
+		}
+	}
+}
+
+/// A [Watcher] checking its file on another thread. See [Watcher::spawn].
+pub struct Watching {
+	current: Arc<Mutex<Arc<Confindent>>>,
+	stop: Arc<AtomicBool>,
+	thread: JoinHandle<Watcher>,
+}
+
+impl Watching {
+	/// The most recent document that parsed.
+	pub fn current(&self) -> Arc<Confindent> {
+		current(&self.current)
+	}
+
+	/// Stop checking the file and get the watcher back.
+	pub fn stop(self) -> Watcher {
+		self.stop.store(true, Ordering::Relaxed);
+		self.thread.thread().unpark();
+
+		match self.thread.join() {
+			Ok(watcher) => watcher,
+			// A subscriber panicked, so pass it on
+			Err(panic) => std::panic::resume_unwind(panic),
+		}
+	}
+}
+
+fn current(current: &Mutex<Arc<Confindent>>) -> Arc<Confindent> {
+	// A subscriber can't panic while this is locked, so it's never poisoned
+	// with a half changed document
+	Arc::clone(&current.lock().unwrap_or_else(|e| e.into_inner()))
+}
+
+fn stamp(path: &Path) -> Option<(SystemTime, u64)> {
+	let metadata = fs::metadata(path).ok()?;
+	Some((metadata.modified().ok()?, metadata.len()))
+}
+
+/// Collect the paths of values that differ between `old` and `new`. Values
+/// with the same key are paired up in order, so the second `Host` is only
+/// compared with the other second `Host`.
+fn changed(old: &[Line], new: &[Line], prefix: &str, paths: &mut Vec<String>) {
+	let (old, new) = (values(old), values(new));
+
+	let position = |values: &[&Value], idx: usize| {
+		values[..idx]
+			.iter()
+			.filter(|other| other.key == values[idx].key)
+			.count()
+	};
+
+	for (idx, value) in old.iter().enumerate() {
+		let path = format!("{prefix}{}", value.key);
+		match nth(&new, &value.key, position(&old, idx)) {
+			None => everything(value, &path, paths),
+			Some(other) => {
+				if value.value != other.value {
+					add(paths, path.clone());
+				}
+				changed(&value.children, &other.children, &format!("{path}/"), paths);
+			}
+		}
+	}
+
+	for (idx, value) in new.iter().enumerate() {
+		if nth(&old, &value.key, position(&new, idx)).is_none() {
+			everything(value, &format!("{prefix}{}", value.key), paths);
+		}
+	}
+}
+
+fn values(lines: &[Line]) -> Vec<&Value> {
+	lines
+		.iter()
+		.filter_map(|line| match line {
+			Line::Value(value) => Some(value),
+			_ => None,
+		})
+		.collect()
+}
+
+/// The `n`th value with the key, starting from zero.
+fn nth<'a>(values: &[&'a Value], key: &str, n: usize) -> Option<&'a Value> {
+	values
+		.iter()
+		.filter(|value| value.key == key)
+		.nth(n)
+		.copied()
+}
+
+/// Add the path of a value, and of everything under it.
+fn everything(value: &Value, path: &str, paths: &mut Vec<String>) {
+	add(paths, path.to_owned());
+	for child in value.values() {
+		everything(child, &format!("{path}/{}", child.key), paths);
+	}
+}
+
+fn add(paths: &mut Vec<String>, path: String) {
+	if !paths.contains(&path) {
+		paths.push(path);
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	#[test]
+	fn changed_paths() {
+		let paths = |old: &str, new: &str| {
+			let (old, new): (Confindent, Confindent) = (old.parse().unwrap(), new.parse().unwrap());
+			let mut paths = vec![];
+			changed(&old.children, &new.children, "", &mut paths);
+			paths
+		};
+
+		assert_eq!(
+			paths(
+				"# a\nHost a\n\tPort 22\nHost b\n\tPort 22",
+				"Host a\n\tPort 22\n\n# b\nHost b\n\tPort 2222"
+			),
+			["Host/Port"]
+		);
+		assert_eq!(
+			paths("A 1\nB\n\tC\n\t\tD", "A 2\nE"),
+			["A", "B", "B/C", "B/C/D", "E"]
+		);
+	}
+
+	#[test]
+	fn reloads() {
+		let path = std::env::temp_dir().join(format!("confindent-watch-{}", std::process::id()));
+		fs::write(&path, "Server\n\tPort 22\n").unwrap();
+
+		let mut watcher = Watcher::new(&path).unwrap();
+		let heard = Arc::new(Mutex::new(vec![]));
+		let hearing = Arc::clone(&heard);
+		watcher.subscribe(move |reload| hearing.lock().unwrap().push(reload.clone()));
+
+		assert_eq!(watcher.check(), None);
+
+		fs::write(&path, "Server\n\tPort 2222\n").unwrap();
+		let Some(Reload::Changed { paths, .. }) = watcher.check() else {
+			panic!("expected a change");
+		};
+		assert_eq!(paths, ["Server/Port"]);
+		assert_eq!(watcher.current().get("Server/Port"), Some("2222"));
+
+		// A broken file keeps the last good document
+		fs::write(&path, "Server\n\tPort 2222\n  Host a\n").unwrap();
+		assert!(matches!(watcher.check(), Some(Reload::Failed(_))));
+		assert_eq!(watcher.current().get("Server/Port"), Some("2222"));
+
+		fs::remove_file(&path).unwrap();
+		assert_eq!(heard.lock().unwrap().len(), 2);
+	}
+}